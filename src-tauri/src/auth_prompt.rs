@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use ssh2::{KeyboardInteractivePrompt, Prompt, Session};
+use tauri::{AppHandle, Manager, State};
+use serde::Serialize;
+
+use crate::error::{EmsError, EmsResult};
+
+// 等待用户回答认证提示的最长时间
+const PROMPT_TIMEOUT: Duration = Duration::from_secs(90);
+
+// 回答为 None 表示用户取消
+type PromptReply = Sender<Option<Vec<String>>>;
+
+// 等待回答的认证提示，按提示ID索引
+#[derive(Clone)]
+pub struct AuthPrompts {
+    pending: Arc<Mutex<HashMap<String, PromptReply>>>,
+}
+
+pub fn init_auth_prompt_state() -> AuthPrompts {
+    AuthPrompts {
+        pending: Arc::new(Mutex::new(HashMap::new())),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PromptField {
+    text: String,
+    // 为 false 时输入内容不应回显（密码、验证码）
+    echo: bool,
+}
+
+// 推送给前端的认证提示
+#[derive(Debug, Clone, Serialize)]
+struct AuthPromptEvent {
+    prompt_id: String,
+    host: String,
+    port: u16,
+    username: String,
+    instructions: String,
+    prompts: Vec<PromptField>,
+    timeout_secs: u64,
+}
+
+// keyboard-interactive 认证：密码提示用已配置的密码自动回答，
+// 其余提示（例如一次性验证码）通过事件转给前端，由 answer_auth_prompt 回答
+pub struct AuthPrompter<'a> {
+    app_handle: &'a AppHandle,
+    host: &'a str,
+    port: u16,
+    password: Option<&'a str>,
+    password_used: bool,
+    // 超时或用户取消，之后的提示不再转给前端
+    aborted: Option<EmsError>,
+}
+
+impl<'a> AuthPrompter<'a> {
+    pub fn new(app_handle: &'a AppHandle, host: &'a str, port: u16, password: Option<&'a str>) -> Self {
+        AuthPrompter {
+            app_handle,
+            host,
+            port,
+            password,
+            password_used: false,
+            aborted: None,
+        }
+    }
+
+    pub fn authenticate(&mut self, session: &Session, username: &str) -> EmsResult<()> {
+        let result = session.userauth_keyboard_interactive(username, self);
+        if let Some(e) = self.aborted.take() {
+            return Err(e);
+        }
+        result?;
+        Ok(())
+    }
+
+    // 转给前端并等待回答
+    fn ask(&self, username: &str, instructions: &str, prompts: &[Prompt<'_>]) -> EmsResult<Vec<String>> {
+        let auth_prompts = self.app_handle
+            .try_state::<AuthPrompts>()
+            .ok_or_else(|| EmsError::AuthFailed("无法向用户请求认证输入".to_string()))?;
+
+        let prompt_id = uuid::Uuid::new_v4().to_string();
+        let (sender, receiver) = mpsc::channel();
+        auth_prompts.pending.lock()?.insert(prompt_id.clone(), sender);
+
+        let event = AuthPromptEvent {
+            prompt_id: prompt_id.clone(),
+            host: self.host.to_string(),
+            port: self.port,
+            username: username.to_string(),
+            instructions: instructions.to_string(),
+            prompts: prompts
+                .iter()
+                .map(|prompt| PromptField { text: prompt.text.to_string(), echo: prompt.echo })
+                .collect(),
+            timeout_secs: PROMPT_TIMEOUT.as_secs(),
+        };
+        println!("🔧 [SSH] {}:{} 请求认证输入: {}", self.host, self.port, prompt_summary(prompts));
+        if let Err(e) = self.app_handle.emit_all("ssh-auth-prompt", event) {
+            auth_prompts.pending.lock()?.remove(&prompt_id);
+            return Err(EmsError::AuthFailed(format!("无法向用户请求认证输入: {}", e)));
+        }
+
+        let result = receiver.recv_timeout(PROMPT_TIMEOUT);
+        auth_prompts.pending.lock()?.remove(&prompt_id);
+        // 通知前端关闭对话框（超时时对话框仍然打开着）
+        let _ = self.app_handle.emit_all("ssh-auth-prompt-closed", &prompt_id);
+
+        match result {
+            Ok(Some(responses)) if responses.len() == prompts.len() => Ok(responses),
+            Ok(Some(_)) => Err(EmsError::InvalidInput("认证回答的数量与提示不一致".to_string())),
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                Err(EmsError::Cancelled(format!("已取消 {}:{} 的认证", self.host, self.port)))
+            }
+            Err(RecvTimeoutError::Timeout) => Err(EmsError::Timeout(format!(
+                "等待 {}:{} 的认证输入超过 {} 秒",
+                self.host,
+                self.port,
+                PROMPT_TIMEOUT.as_secs()
+            ))),
+        }
+    }
+
+    // 只有一个不回显的密码提示时，用已配置的密码回答（每次认证只用一次，密码错误时转给用户）
+    fn auto_answer(&mut self, prompts: &[Prompt<'_>]) -> Option<Vec<String>> {
+        let password = self.password.filter(|password| !password.is_empty())?;
+        let [prompt] = prompts else {
+            return None;
+        };
+        if self.password_used || prompt.echo || !prompt.text.to_lowercase().contains("password") {
+            return None;
+        }
+        self.password_used = true;
+        Some(vec![password.to_string()])
+    }
+}
+
+impl KeyboardInteractivePrompt for AuthPrompter<'_> {
+    fn prompt<'b>(&mut self, username: &str, instructions: &str, prompts: &[Prompt<'b>]) -> Vec<String> {
+        // 服务器可能只发送说明文字，不需要回答
+        if prompts.is_empty() || self.aborted.is_some() {
+            return vec![String::new(); prompts.len()];
+        }
+        if let Some(responses) = self.auto_answer(prompts) {
+            return responses;
+        }
+
+        match self.ask(username, instructions, prompts) {
+            Ok(responses) => responses,
+            Err(e) => {
+                println!("⚠️ [SSH] 认证输入失败: {}", e);
+                self.aborted = Some(e);
+                vec![String::new(); prompts.len()]
+            }
+        }
+    }
+}
+
+fn prompt_summary(prompts: &[Prompt<'_>]) -> String {
+    prompts
+        .iter()
+        .map(|prompt| prompt.text.trim())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// 前端回答认证提示，responses 为空表示取消认证
+#[tauri::command]
+pub async fn answer_auth_prompt(
+    prompt_id: String,
+    responses: Option<Vec<String>>,
+    auth_prompts: State<'_, AuthPrompts>,
+) -> EmsResult<()> {
+    let sender = auth_prompts.pending
+        .lock()?
+        .remove(&prompt_id)
+        .ok_or_else(|| EmsError::NotFound("认证提示已超时或已被回答".to_string()))?;
+    sender
+        .send(responses)
+        .map_err(|_| EmsError::NotFound("认证已结束".to_string()))
+}
@@ -0,0 +1,127 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::command::ShellCommand;
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{SharedSshSession, run_command};
+
+// 远程命令不存在时的退出代码
+const COMMAND_NOT_FOUND: i32 = 127;
+
+// 校验算法，远程没有 sha256sum 时退回 md5sum
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Md5,
+}
+
+impl ChecksumAlgorithm {
+    fn command(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256sum",
+            ChecksumAlgorithm::Md5 => "md5sum",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "SHA-256",
+            ChecksumAlgorithm::Md5 => "MD5",
+        }
+    }
+}
+
+// 传输后的校验结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChecksumVerification {
+    pub algorithm: ChecksumAlgorithm,
+    pub local: String,
+    pub remote: String,
+}
+
+impl ChecksumVerification {
+    pub fn matched(&self) -> bool {
+        self.local == self.remote
+    }
+
+    pub fn to_error(&self, remote_path: &str) -> EmsError {
+        EmsError::ChecksumMismatch {
+            path: remote_path.to_string(),
+            algorithm: self.algorithm.name().to_string(),
+            local: self.local.clone(),
+            remote: self.remote.clone(),
+        }
+    }
+}
+
+// 分别计算本地文件和远程文件的校验和，调用方不能持有会话锁
+pub fn verify_checksum(ssh_session: &SharedSshSession, local_path: &Path, remote_path: &str) -> EmsResult<ChecksumVerification> {
+    let (algorithm, remote) = remote_checksum(ssh_session, remote_path)?;
+    let local = local_checksum(algorithm, local_path)
+        .map_err(|e| EmsError::Io(format!("无法计算本地文件 '{}' 的校验和: {}", local_path.display(), e)))?;
+    Ok(ChecksumVerification { algorithm, local, remote })
+}
+
+fn remote_checksum(ssh_session: &SharedSshSession, remote_path: &str) -> EmsResult<(ChecksumAlgorithm, String)> {
+    for algorithm in [ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Md5] {
+        let command = ShellCommand::new(algorithm.command()).raw("--").arg(remote_path);
+        let output = run_command(ssh_session, &command.to_string())?;
+        if output.exit_code == Some(COMMAND_NOT_FOUND) {
+            continue;
+        }
+        if !output.success() {
+            return Err(output.to_error());
+        }
+        let checksum = parse_checksum_output(&output.stdout)
+            .ok_or_else(|| EmsError::ParseError(format!("无法解析 {} 的输出: {}", algorithm.command(), output.stdout.trim())))?;
+        return Ok((algorithm, checksum));
+    }
+    Err(EmsError::NotFound("远程主机上没有 sha256sum 或 md5sum 命令".to_string()))
+}
+
+// 输出格式为 "<校验和>  <文件名>"，文件名含反斜杠或换行时行首多一个 \
+fn parse_checksum_output(stdout: &str) -> Option<String> {
+    let checksum = stdout.split_whitespace().next()?.trim_start_matches('\\');
+    if checksum.is_empty() || !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(checksum.to_ascii_lowercase())
+}
+
+fn local_checksum(algorithm: ChecksumAlgorithm, path: &Path) -> io::Result<String> {
+    match algorithm {
+        ChecksumAlgorithm::Sha256 => digest_file::<Sha256>(path),
+        ChecksumAlgorithm::Md5 => digest_file::<Md5>(path),
+    }
+}
+
+fn digest_file<D: Digest + Write>(path: &Path) -> io::Result<String> {
+    let mut hasher = D::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_checksum_output() {
+        assert_eq!(
+            parse_checksum_output("D41D8CD98F00B204E9800998ECF8427E  /ems/app.bin\n").as_deref(),
+            Some("d41d8cd98f00b204e9800998ecf8427e")
+        );
+        // 文件名中含反斜杠时的转义格式
+        assert_eq!(
+            parse_checksum_output("\\d41d8cd98f00b204e9800998ecf8427e  /ems/a\\\\b\n").as_deref(),
+            Some("d41d8cd98f00b204e9800998ecf8427e")
+        );
+        assert_eq!(parse_checksum_output(""), None);
+        assert_eq!(parse_checksum_output("sha256sum: /ems/app.bin: No such file"), None);
+    }
+}
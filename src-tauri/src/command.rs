@@ -0,0 +1,198 @@
+use std::fmt;
+use std::net::Ipv4Addr;
+
+use crate::error::{EmsError, EmsResult};
+
+// 构建在远程 POSIX sh 中执行的命令
+// 用户提供的参数一律经过 quote，服务名、接口名等标识符先校验再使用
+
+// 不需要引号的字符，其余情况统一用单引号包裹
+fn is_safe_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '/' | ':' | '@' | '%' | '+' | '=' | ',')
+}
+
+// 把任意字符串转换为 sh 中的单个参数
+// 单引号内没有任何特殊字符，单引号本身写作 '\''
+pub fn quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(is_safe_char) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// 一条命令（或用管道连接的多条命令）
+#[derive(Debug, Clone)]
+pub struct ShellCommand {
+    parts: Vec<String>,
+}
+
+impl ShellCommand {
+    pub fn new(program: &str) -> Self {
+        ShellCommand { parts: vec![quote(program)] }
+    }
+
+    // 添加参数，自动加引号
+    pub fn arg(mut self, arg: impl AsRef<str>) -> Self {
+        self.parts.push(quote(arg.as_ref()));
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.parts.extend(args.into_iter().map(|arg| quote(arg.as_ref())));
+        self
+    }
+
+    // 添加固定的shell片段，例如重定向；只能用于代码中的字面量
+    pub fn raw(mut self, fragment: &'static str) -> Self {
+        self.parts.push(fragment.to_string());
+        self
+    }
+
+    // 把输出通过管道交给下一条命令
+    pub fn pipe(mut self, next: ShellCommand) -> Self {
+        self.parts.push("|".to_string());
+        self.parts.extend(next.parts);
+        self
+    }
+}
+
+impl fmt::Display for ShellCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.parts.join(" "))
+    }
+}
+
+// systemd 单元名：字母数字和 :_.-@\，可以带 .service 后缀
+pub fn validate_unit_name(name: &str) -> EmsResult<&str> {
+    let valid = !name.is_empty()
+        && name.len() <= 255
+        && !name.starts_with('-')
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, ':' | '_' | '.' | '-' | '@' | '\\'));
+    if !valid {
+        return Err(EmsError::InvalidInput(format!("无效的服务名: {}", name)));
+    }
+    Ok(name)
+}
+
+// Linux 网络接口名：最长15字节，不能包含空白和 /
+pub fn validate_interface_name(name: &str) -> EmsResult<&str> {
+    let valid = !name.is_empty()
+        && name.len() <= 15
+        && name != "."
+        && name != ".."
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | ':' | '@'));
+    if !valid {
+        return Err(EmsError::InvalidInput(format!("无效的网络接口名: {}", name)));
+    }
+    Ok(name)
+}
+
+// IPv4 地址，返回规范格式
+pub fn validate_ipv4(address: &str) -> EmsResult<String> {
+    address.trim()
+        .parse::<Ipv4Addr>()
+        .map(|address| address.to_string())
+        .map_err(|_| EmsError::InvalidInput(format!("无效的IP地址: {}", address)))
+}
+
+// 远程绝对路径，不能包含换行和空字符
+pub fn validate_remote_path(path: &str) -> EmsResult<&str> {
+    if !path.starts_with('/') || path.contains(['\n', '\r', '\0']) {
+        return Err(EmsError::InvalidInput(format!("无效的远程路径: {}", path)));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_only_when_needed() {
+        assert_eq!(quote("nginx.service"), "nginx.service");
+        assert_eq!(quote("/etc/systemd/system/ems@1.service"), "/etc/systemd/system/ems@1.service");
+        assert_eq!(quote(""), "''");
+        assert_eq!(quote("my file"), "'my file'");
+        assert_eq!(quote("it's"), "'it'\\''s'");
+        assert_eq!(quote("*"), "'*'");
+    }
+
+    #[test]
+    fn builds_commands() {
+        let command = ShellCommand::new("systemctl")
+            .arg("status")
+            .arg("ems.service")
+            .args(["--no-pager", "-l"]);
+        assert_eq!(command.to_string(), "systemctl status ems.service --no-pager -l");
+
+        let command = ShellCommand::new("systemctl")
+            .args(["list-unit-files", "--type=service"])
+            .pipe(ShellCommand::new("grep").args(["-i", "-F", "-e", "a b"]));
+        assert_eq!(command.to_string(), "systemctl list-unit-files --type=service | grep -i -F -e 'a b'");
+
+        let command = ShellCommand::new("find").arg("/opt").arg("-name").arg("*ems*").raw("2>/dev/null");
+        assert_eq!(command.to_string(), "find /opt -name '*ems*' 2>/dev/null");
+    }
+
+    #[test]
+    fn validates_unit_names() {
+        assert!(validate_unit_name("nginx").is_ok());
+        assert!(validate_unit_name("ems-agent@2.service").is_ok());
+        assert!(validate_unit_name("dev-disk-by\\x2dlabel.mount").is_ok());
+        for name in ["", "-h", "a b", "nginx; reboot", "x$(id)", "a`id`", "a'b", "a/b", "a\nb"] {
+            assert!(validate_unit_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn validates_interface_names() {
+        assert!(validate_interface_name("eth0").is_ok());
+        assert!(validate_interface_name("enp3s0.100").is_ok());
+        assert!(validate_interface_name("br-lan").is_ok());
+        for name in ["", ".", "..", "-eth0", "eth0 up", "eth0;reboot", "a/b", "averyverylongname0"] {
+            assert!(validate_interface_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn validates_addresses_and_paths() {
+        assert_eq!(validate_ipv4(" 192.168.1.10 ").unwrap(), "192.168.1.10");
+        assert!(validate_ipv4("192.168.1.10; reboot").is_err());
+        assert!(validate_ipv4("256.1.1.1").is_err());
+
+        assert!(validate_remote_path("/etc/systemd/system/ems.service").is_ok());
+        assert!(validate_remote_path("relative/path").is_err());
+        assert!(validate_remote_path("/tmp/a\nrm -rf /").is_err());
+    }
+
+    // 在本地 sh 中执行，确认注入尝试原样作为一个参数传递
+    #[cfg(unix)]
+    #[test]
+    fn injection_attempts_stay_literal() {
+        use std::process::Command;
+
+        let attempts = [
+            "'; touch /tmp/ems_injected; echo '",
+            "$(touch /tmp/ems_injected)",
+            "`touch /tmp/ems_injected`",
+            "a && touch /tmp/ems_injected",
+            "a | touch /tmp/ems_injected",
+            "line1\nline2",
+            "$HOME ${PATH} \\ \" ' * ? ~",
+            "-n",
+        ];
+        for attempt in attempts {
+            let script = ShellCommand::new("printf").arg("%s").arg(attempt).to_string();
+            let output = Command::new("sh").arg("-c").arg(&script).output().unwrap();
+            assert!(output.status.success(), "{}", script);
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), attempt);
+        }
+        assert!(!std::path::Path::new("/tmp/ems_injected").exists());
+    }
+}
@@ -0,0 +1,394 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use ssh2::FileStat;
+use tauri::State;
+use serde::Serialize;
+
+use crate::error::{EmsError, EmsResult};
+use crate::sftp::open_sftp;
+use crate::ssh::{SharedSshSession, SshSessions, run_blocking, run_command};
+
+// 最多返回的候选项数量
+const MAX_CANDIDATES: usize = 200;
+// $PATH 中的命令变化不频繁，缓存时间较长
+const COMMAND_CACHE_TTL: Duration = Duration::from_secs(300);
+const UNIT_CACHE_TTL: Duration = Duration::from_secs(30);
+// 列出已安装的单元文件和已加载的单元（包括模板实例）
+const LIST_UNITS_COMMAND: &str = "systemctl list-unit-files --no-legend --no-pager 2>/dev/null; \
+    systemctl list-units --all --no-legend --no-pager --plain 2>/dev/null";
+// 不在 $PATH 中的常用shell内置命令
+const SHELL_BUILTINS: &[&str] = &["cd", "echo", "export", "pwd", "source", "type", "umask", "unset"];
+const SYSTEMCTL_VERBS: &[&str] = &[
+    "cat", "daemon-reload", "disable", "enable", "is-active", "is-enabled", "is-failed",
+    "kill", "list-timers", "list-unit-files", "list-units", "mask", "reload", "reset-failed",
+    "restart", "show", "start", "status", "stop", "try-restart", "unmask",
+];
+
+// 会话内缓存的命令和单元列表
+#[derive(Default)]
+pub struct CompletionCache {
+    commands: Option<(Instant, Arc<Vec<String>>)>,
+    units: Option<(Instant, Arc<Vec<String>>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateKind {
+    Directory,
+    File,
+    Command,
+    Unit,
+    Subcommand,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Candidate {
+    // 替换输入中 [start, cursor) 的内容，已按shell规则转义
+    value: String,
+    kind: CandidateKind,
+}
+
+// 补全结果，位置与前端输入框一致按UTF-16计算
+#[derive(Debug, Serialize)]
+pub struct CompletionResult {
+    start: usize,
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Context {
+    Command,
+    Path,
+    Unit,
+    SystemctlVerb,
+}
+
+// 正在补全的单词
+#[derive(Debug)]
+struct Word {
+    // 在输入中的起始位置（UTF-16）
+    start: usize,
+    // 去掉反斜杠转义后的内容
+    text: String,
+}
+
+// 终端输入的补全：命令名、文件路径以及 systemctl/journalctl 的单元名
+// cursor 为光标位置（UTF-16，与浏览器 selectionStart 一致）
+#[tauri::command]
+pub async fn complete(
+    prefix: String,
+    cursor: usize,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+) -> EmsResult<CompletionResult> {
+    let ssh_session = ssh_state.get(&session_id)?;
+    let before_cursor = utf16_prefix(&prefix, cursor);
+    let (context, word) = parse_input(before_cursor);
+
+    run_blocking(move || {
+        let candidates = match context {
+            Context::Command => complete_commands(&ssh_session, &word.text)?,
+            Context::Path => complete_paths(&ssh_session, &word.text)?,
+            Context::Unit => complete_units(&ssh_session, &word.text)?,
+            Context::SystemctlVerb => SYSTEMCTL_VERBS
+                .iter()
+                .filter(|verb| verb.starts_with(word.text.as_str()))
+                .map(|verb| Candidate { value: verb.to_string(), kind: CandidateKind::Subcommand })
+                .collect(),
+        };
+        Ok(CompletionResult { start: word.start, candidates })
+    }).await
+}
+
+// 截取光标之前的内容
+fn utf16_prefix(input: &str, cursor: usize) -> &str {
+    let mut units = 0;
+    for (index, c) in input.char_indices() {
+        if units >= cursor {
+            return &input[..index];
+        }
+        units += c.len_utf16();
+    }
+    input
+}
+
+// 拆分出当前命令的参数和正在输入的单词，支持反斜杠转义，不处理引号
+fn parse_input(input: &str) -> (Context, Word) {
+    let mut args: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut current_start = 0;
+    let mut position = 0;
+    let mut escaped = false;
+
+    for c in input.chars() {
+        let width = c.len_utf16();
+        if escaped {
+            current.push(c);
+            escaped = false;
+        } else if c == '\\' {
+            if current.is_empty() {
+                current_start = position;
+            }
+            escaped = true;
+        } else if c.is_whitespace() || matches!(c, '|' | ';' | '&') {
+            if !current.is_empty() {
+                args.push(std::mem::take(&mut current));
+            }
+            // 管道和命令分隔符之后是新的命令
+            if !c.is_whitespace() {
+                args.clear();
+            }
+        } else {
+            if current.is_empty() {
+                current_start = position;
+            }
+            current.push(c);
+        }
+        position += width;
+    }
+    if current.is_empty() && !escaped {
+        current_start = position;
+    }
+
+    // sudo 后面是真正执行的命令
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .skip_while(|arg| *arg == "sudo" || arg.starts_with('-'))
+        .collect();
+
+    let context = match args.first() {
+        None if current.contains('/') => Context::Path,
+        None => Context::Command,
+        Some(&"systemctl") if !current.contains('/') => {
+            let has_verb = args[1..].iter().any(|arg| !arg.starts_with('-'));
+            if has_verb {
+                Context::Unit
+            } else if current.starts_with('-') {
+                Context::Path
+            } else {
+                Context::SystemctlVerb
+            }
+        }
+        Some(&"journalctl") if args.last() == Some(&"-u") => Context::Unit,
+        Some(_) => Context::Path,
+    };
+
+    (context, Word { start: current_start, text: current })
+}
+
+// 用反斜杠转义shell特殊字符，与 parse_input 对应
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_whitespace() || "\\'\"$`&|;<>()*?![]{}#".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn complete_paths(ssh_session: &SharedSshSession, text: &str) -> EmsResult<Vec<Candidate>> {
+    let (current_directory, home_directory) = {
+        let ssh_guard = ssh_session.lock()?;
+        (ssh_guard.current_directory.clone(), ssh_guard.environment.home_directory.clone())
+    };
+
+    // 已输入的目录部分（含末尾的 /）和文件名前缀
+    let (dir_part, name_prefix) = match text.rfind('/') {
+        Some(index) => text.split_at(index + 1),
+        None => ("", text),
+    };
+    let directory = if dir_part.starts_with('/') {
+        dir_part.to_string()
+    } else if let Some(rest) = dir_part.strip_prefix("~/") {
+        format!("{}/{}", home_directory.trim_end_matches('/'), rest)
+    } else {
+        format!("{}/{}", current_directory.trim_end_matches('/'), dir_part)
+    };
+
+    let entries = read_dir(ssh_session, &directory)?;
+    let mut candidates: Vec<Candidate> = entries
+        .into_iter()
+        .filter(|(name, _)| name.starts_with(name_prefix) && name != "." && name != "..")
+        // 没有输入 . 时不显示隐藏文件
+        .filter(|(name, _)| name_prefix.starts_with('.') || !name.starts_with('.'))
+        .take(MAX_CANDIDATES)
+        .map(|(name, is_dir)| Candidate {
+            value: format!("{}{}", escape(&format!("{}{}", dir_part, name)), if is_dir { "/" } else { "" }),
+            kind: if is_dir { CandidateKind::Directory } else { CandidateKind::File },
+        })
+        .collect();
+    candidates.sort_by(|a, b| a.value.cmp(&b.value));
+    Ok(candidates)
+}
+
+// 通过SFTP列出目录，返回名称和是否为目录（指向目录的符号链接也算目录）
+fn read_dir(ssh_session: &SharedSshSession, directory: &str) -> EmsResult<Vec<(String, bool)>> {
+    let ssh_guard = ssh_session.lock()?;
+    let session = ssh_guard.session.as_ref().ok_or(EmsError::NotConnected)?;
+    let sftp = session.sftp()
+        .map_err(|e| EmsError::SftpError(format!("无法创建SFTP会话: {}", e)))?;
+
+    // 目录不存在或没有权限时没有候选项
+    let Ok(entries) = sftp.readdir(Path::new(directory)) else {
+        return Ok(Vec::new());
+    };
+    Ok(entries
+        .into_iter()
+        .filter_map(|(path, stat)| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            let is_dir = stat.is_dir() || (is_symlink(&stat) && sftp.stat(&path).is_ok_and(|target| target.is_dir()));
+            Some((name, is_dir))
+        })
+        .collect())
+}
+
+fn is_symlink(stat: &FileStat) -> bool {
+    stat.file_type().is_symlink()
+}
+
+fn complete_commands(ssh_session: &SharedSshSession, text: &str) -> EmsResult<Vec<Candidate>> {
+    let commands = cached_commands(ssh_session)?;
+    Ok(commands
+        .iter()
+        .filter(|command| command.starts_with(text))
+        .take(MAX_CANDIDATES)
+        .map(|command| Candidate { value: escape(command), kind: CandidateKind::Command })
+        .collect())
+}
+
+// $PATH 中所有可执行文件的名称
+fn cached_commands(ssh_session: &SharedSshSession) -> EmsResult<Arc<Vec<String>>> {
+    let search_path = {
+        let ssh_guard = ssh_session.lock()?;
+        if let Some((loaded_at, ref commands)) = ssh_guard.completion_cache.commands {
+            if loaded_at.elapsed() < COMMAND_CACHE_TTL {
+                return Ok(commands.clone());
+            }
+        }
+        ssh_guard.environment.search_path.clone()
+    };
+
+    // 所有目录共用一个SFTP会话，会话锁只在每次列目录时持有
+    let remote_fs = open_sftp(ssh_session)?;
+    let mut commands: Vec<String> = SHELL_BUILTINS.iter().map(|builtin| builtin.to_string()).collect();
+    for dir in &search_path {
+        let Ok(entries) = remote_fs.with(|sftp| sftp.readdir(Path::new(dir))) else {
+            continue;
+        };
+        commands.extend(entries.into_iter().filter_map(|(path, stat)| {
+            // 符号链接的权限位没有意义，一律视为可执行
+            let executable = is_symlink(&stat) || (!stat.is_dir() && stat.perm.is_some_and(|perm| perm & 0o111 != 0));
+            if !executable {
+                return None;
+            }
+            Some(path.file_name()?.to_string_lossy().into_owned())
+        }));
+    }
+    commands.sort();
+    commands.dedup();
+
+    let commands = Arc::new(commands);
+    ssh_session.lock()?.completion_cache.commands = Some((Instant::now(), commands.clone()));
+    Ok(commands)
+}
+
+fn complete_units(ssh_session: &SharedSshSession, text: &str) -> EmsResult<Vec<Candidate>> {
+    let units = cached_units(ssh_session)?;
+    Ok(units
+        .iter()
+        .filter(|unit| unit.starts_with(text))
+        .take(MAX_CANDIDATES)
+        .map(|unit| Candidate { value: escape(unit), kind: CandidateKind::Unit })
+        .collect())
+}
+
+fn cached_units(ssh_session: &SharedSshSession) -> EmsResult<Arc<Vec<String>>> {
+    {
+        let ssh_guard = ssh_session.lock()?;
+        if let Some((loaded_at, ref units)) = ssh_guard.completion_cache.units {
+            if loaded_at.elapsed() < UNIT_CACHE_TTL {
+                return Ok(units.clone());
+            }
+        }
+    }
+
+    // 没有systemd的设备上命令失败，此时没有候选项
+    let output = run_command(ssh_session, LIST_UNITS_COMMAND)?;
+    let mut units: Vec<String> = output.stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .filter(|unit| unit.contains('.'))
+        .map(str::to_string)
+        .collect();
+    units.sort();
+    units.dedup();
+
+    let units = Arc::new(units);
+    ssh_session.lock()?.completion_cache.units = Some((Instant::now(), units.clone()));
+    Ok(units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> (Context, usize, String) {
+        let (context, word) = parse_input(input);
+        (context, word.start, word.text)
+    }
+
+    #[test]
+    fn cuts_input_at_utf16_cursor() {
+        assert_eq!(utf16_prefix("ls /var/log", 7), "ls /var");
+        assert_eq!(utf16_prefix("cat 中文.txt", 6), "cat 中文");
+        // emoji 占两个UTF-16单位
+        assert_eq!(utf16_prefix("cd 😀/logs", 5), "cd 😀");
+        // 光标落在代理对中间时保留整个字符
+        assert_eq!(utf16_prefix("cd 😀/logs", 4), "cd 😀");
+        assert_eq!(utf16_prefix("ls", 0), "");
+        assert_eq!(utf16_prefix("ls", 10), "ls");
+    }
+
+    #[test]
+    fn detects_completion_context() {
+        assert_eq!(parse("sys"), (Context::Command, 0, "sys".to_string()));
+        assert_eq!(parse("./run"), (Context::Path, 0, "./run".to_string()));
+        assert_eq!(parse("ls /var/lo"), (Context::Path, 3, "/var/lo".to_string()));
+        assert_eq!(parse("ls "), (Context::Path, 3, String::new()));
+        assert_eq!(parse("cat a.log | gr"), (Context::Command, 12, "gr".to_string()));
+        assert_eq!(parse("cd /tmp; ls"), (Context::Command, 9, "ls".to_string()));
+        assert_eq!(parse("systemctl res"), (Context::SystemctlVerb, 10, "res".to_string()));
+        assert_eq!(parse("sudo -E systemctl restart ng"), (Context::Unit, 26, "ng".to_string()));
+        assert_eq!(parse("systemctl cat /etc/"), (Context::Path, 14, "/etc/".to_string()));
+        assert_eq!(parse("journalctl -u ems"), (Context::Unit, 14, "ems".to_string()));
+        assert_eq!(parse("journalctl -f"), (Context::Path, 11, "-f".to_string()));
+    }
+
+    #[test]
+    fn parses_escaped_spaces_and_multibyte_words() {
+        assert_eq!(parse(r"ls My\ Doc"), (Context::Path, 3, "My Doc".to_string()));
+        assert_eq!(parse(r"ls \ lead"), (Context::Path, 3, " lead".to_string()));
+        // 起始位置按UTF-16计算
+        assert_eq!(parse("cat 中文 日志"), (Context::Path, 7, "日志".to_string()));
+        assert_eq!(parse("cat 😀 b"), (Context::Path, 7, "b".to_string()));
+        // 引号不做处理，按普通字符保留
+        assert_eq!(parse(r#"cat "a b"#), (Context::Path, 7, "b".to_string()));
+    }
+
+    #[test]
+    fn escapes_shell_special_characters() {
+        assert_eq!(escape("app.log"), "app.log");
+        assert_eq!(escape("My Doc's $HOME (1).txt"), r"My\ Doc\'s\ \$HOME\ \(1\).txt");
+        assert_eq!(escape(r#"a"b\c"#), r#"a\"b\\c"#);
+        assert_eq!(escape("日志 备份"), r"日志\ 备份");
+
+        // 转义后的候选项插入输入后能解析回原来的名称
+        for name in ["My Doc's $HOME (1).txt", r#"a"b\c"#, "日志 备份", "x|y;z&w"] {
+            assert_eq!(parse(&format!("ls {}", escape(name))).2, name);
+        }
+    }
+}
@@ -0,0 +1,661 @@
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+use ssh2::FileStat;
+use tauri::{AppHandle, State};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::AppState;
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{SshSessions, run_blocking};
+use crate::sftp::{RemoteFs, open_sftp};
+use crate::logger::add_log_internal;
+use crate::file_transfer::{
+    DEFAULT_DIR_MODE, TransferDirection, TransferProgress, copy_with_progress, create_remote_dir_all,
+    partial_path, preserve_attributes, replace_remote_file,
+};
+
+// 下载时跟随符号链接的最大目录深度，防止链接成环
+const MAX_DEPTH: usize = 64;
+
+// 符号链接的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymlinkPolicy {
+    // 跳过符号链接
+    #[default]
+    Skip,
+    // 传输链接指向的文件或目录
+    Follow,
+    // 在目标端创建相同的符号链接
+    Preserve,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DirectoryTransferConfig {
+    local_path: String,
+    remote_path: String,
+    // 只传输匹配的文件，为空时传输全部文件
+    #[serde(default)]
+    include: Vec<String>,
+    // 排除匹配的文件和目录（目录被排除时不再进入）
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    symlinks: SymlinkPolicy,
+    // 保留权限和修改时间
+    #[serde(default)]
+    preserve_attributes: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryStatus {
+    Transferred,
+    Skipped,
+    Failed,
+}
+
+// 单个文件、目录或符号链接的传输结果
+#[derive(Debug, Serialize)]
+pub struct TransferEntry {
+    // 相对于传输根目录的路径，使用 / 分隔
+    path: String,
+    status: EntryStatus,
+    bytes: u64,
+    message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DirectoryTransferResult {
+    success: bool,
+    message: String,
+    transfer_id: String,
+    files_transferred: usize,
+    files_skipped: usize,
+    files_failed: usize,
+    bytes_transferred: u64,
+    entries: Vec<TransferEntry>,
+}
+
+impl DirectoryTransferResult {
+    fn new(transfer_id: String, entries: Vec<TransferEntry>) -> Self {
+        let count = |status| entries.iter().filter(|entry| entry.status == status).count();
+        let files_transferred = count(EntryStatus::Transferred);
+        let files_skipped = count(EntryStatus::Skipped);
+        let files_failed = count(EntryStatus::Failed);
+        let bytes_transferred = entries.iter().map(|entry| entry.bytes).sum();
+
+        DirectoryTransferResult {
+            success: files_failed == 0,
+            message: format!("传输 {} 项，跳过 {} 项，失败 {} 项", files_transferred, files_skipped, files_failed),
+            transfer_id,
+            files_transferred,
+            files_skipped,
+            files_failed,
+            bytes_transferred,
+            entries,
+        }
+    }
+}
+
+// 待传输的一项，先列出全部再传输，以便计算总大小
+enum PlannedEntry {
+    Directory { relative: String, perm: Option<u32> },
+    File { relative: String, source: PathBuf, stat: FileStat },
+    Symlink { relative: String, target: PathBuf },
+    Skipped { relative: String, reason: String },
+}
+
+// include/exclude 过滤：不含 / 的模式匹配任意层级的文件名，含 / 的模式匹配相对路径
+struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: GlobSet,
+}
+
+impl PathFilter {
+    fn new(include: &[String], exclude: &[String]) -> EmsResult<Self> {
+        let include = if include.is_empty() { None } else { Some(build_glob_set(include)?) };
+        Ok(PathFilter {
+            include,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    fn is_excluded(&self, relative: &str) -> bool {
+        self.exclude.is_match(relative)
+    }
+
+    fn includes_file(&self, relative: &str) -> bool {
+        self.include.as_ref().is_none_or(|include| include.is_match(relative))
+    }
+}
+
+fn build_glob_set(patterns: &[String]) -> EmsResult<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns.iter().map(|pattern| pattern.trim()).filter(|pattern| !pattern.is_empty()) {
+        let pattern = pattern.trim_end_matches('/');
+        let pattern = if pattern.contains('/') {
+            pattern.trim_start_matches("./").trim_start_matches('/').to_string()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let glob: Glob = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| EmsError::InvalidInput(format!("无效的匹配模式 '{}': {}", pattern, e)))?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map_err(|e| EmsError::InvalidInput(format!("无效的匹配模式: {}", e)))
+}
+
+fn join_remote(root: &str, relative: &str) -> PathBuf {
+    if relative.is_empty() {
+        PathBuf::from(root)
+    } else {
+        Path::new(root).join(relative)
+    }
+}
+
+// 上传本地目录，remote_path 为远程目标目录（不存在时创建）
+#[tauri::command]
+pub async fn upload_directory(
+    config: DirectoryTransferConfig,
+    session_id: String,
+    transfer_id: Option<String>,
+    app_handle: AppHandle,
+    ssh_state: State<'_, SshSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<DirectoryTransferResult> {
+    let ssh_state = ssh_state.get(&session_id)?;
+    let app_state = app_state.inner().clone();
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    run_blocking(move || {
+        let log_message = format!("开始上传目录: {} -> {}", config.local_path, config.remote_path);
+        add_log_internal(&app_state, "INFO", &log_message);
+        println!("🔧 [SFTP] {}", log_message);
+
+        let local_root = PathBuf::from(&config.local_path);
+        if !local_root.is_dir() {
+            return Err(EmsError::InvalidInput(format!("本地路径不是目录: {}", config.local_path)));
+        }
+        let filter = PathFilter::new(&config.include, &config.exclude)?;
+        let plan = plan_local(&local_root, &filter, config.symlinks)?;
+
+        // 每次SFTP操作才获取会话锁，传输期间同一连接上的其他操作不受影响
+        let remote_fs = open_sftp(&ssh_state)?;
+        create_remote_dir_all(&remote_fs, Path::new(&config.remote_path), DEFAULT_DIR_MODE)?;
+
+        let total_bytes = total_size(&plan);
+        let mut progress = TransferProgress::new(&app_handle, &transfer_id, TransferDirection::Upload, &config.remote_path, total_bytes);
+        let mut entries = Vec::with_capacity(plan.len());
+        // 创建失败的目录，其中的内容不再尝试
+        let mut failed_dirs: Vec<String> = Vec::new();
+
+        for entry in plan {
+            let relative = planned_path(&entry).to_string();
+            if let Some(dir) = failed_dirs.iter().find(|dir| relative.starts_with(&format!("{}/", dir))) {
+                entries.push(failed(relative, format!("目录创建失败: {}", dir)));
+                continue;
+            }
+            let remote_path = join_remote(&config.remote_path, &relative);
+
+            let result = match entry {
+                PlannedEntry::Directory { perm, .. } => {
+                    let mode = perm.filter(|_| config.preserve_attributes).map_or(DEFAULT_DIR_MODE, |perm| (perm & 0o7777) as i32);
+                    let result = create_remote_dir_all(&remote_fs, &remote_path, mode);
+                    if result.is_err() {
+                        failed_dirs.push(relative.clone());
+                    }
+                    result.map(|_| 0)
+                }
+                PlannedEntry::File { source, stat, .. } => {
+                    progress.set_path(&remote_path.to_string_lossy());
+                    upload_one(&remote_fs, &source, &remote_path, &stat, config.preserve_attributes, &mut progress)
+                }
+                PlannedEntry::Symlink { target, .. } => remote_fs
+                    .with(|sftp| sftp.symlink(&target, &remote_path))
+                    .map(|_| 0)
+                    .map_err(|e| EmsError::SftpError(format!("无法创建远程符号链接: {}", e))),
+                PlannedEntry::Skipped { reason, .. } => {
+                    entries.push(skipped(relative, reason));
+                    continue;
+                }
+            };
+            entries.push(entry_result(relative, result));
+        }
+        progress.finish();
+
+        let result = DirectoryTransferResult::new(transfer_id, entries);
+        let log_message = format!("目录上传完成: {} -> {}, {}", config.local_path, config.remote_path, result.message);
+        add_log_internal(&app_state, if result.success { "INFO" } else { "WARN" }, &log_message);
+        println!("✅ [SFTP] {}", log_message);
+        Ok(result)
+    }).await
+}
+
+// 下载远程目录，local_path 为本地目标目录（不存在时创建）
+#[tauri::command]
+pub async fn download_directory(
+    config: DirectoryTransferConfig,
+    session_id: String,
+    transfer_id: Option<String>,
+    app_handle: AppHandle,
+    ssh_state: State<'_, SshSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<DirectoryTransferResult> {
+    let ssh_state = ssh_state.get(&session_id)?;
+    let app_state = app_state.inner().clone();
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    run_blocking(move || {
+        let log_message = format!("开始下载目录: {} -> {}", config.remote_path, config.local_path);
+        add_log_internal(&app_state, "INFO", &log_message);
+        println!("🔧 [SFTP] {}", log_message);
+
+        let filter = PathFilter::new(&config.include, &config.exclude)?;
+
+        // 每次SFTP操作才获取会话锁，传输期间同一连接上的其他操作不受影响
+        let remote_fs = open_sftp(&ssh_state)?;
+
+        let remote_root = PathBuf::from(&config.remote_path);
+        let root_stat = remote_fs.with(|sftp| sftp.stat(&remote_root))
+            .map_err(|e| EmsError::SftpError(format!("无法访问远程目录 '{}': {}", config.remote_path, e)))?;
+        if !root_stat.is_dir() {
+            return Err(EmsError::InvalidInput(format!("远程路径不是目录: {}", config.remote_path)));
+        }
+        let plan = plan_remote(&remote_fs, &remote_root, &filter, config.symlinks)?;
+
+        let local_root = PathBuf::from(&config.local_path);
+        fs::create_dir_all(&local_root)
+            .map_err(|e| EmsError::Io(format!("无法创建本地目录 '{}': {}", local_root.display(), e)))?;
+
+        let total_bytes = total_size(&plan);
+        let mut progress = TransferProgress::new(&app_handle, &transfer_id, TransferDirection::Download, &config.remote_path, total_bytes);
+        let mut entries = Vec::with_capacity(plan.len());
+
+        for entry in plan {
+            let relative = planned_path(&entry).to_string();
+            let local_path = local_root.join(&relative);
+
+            let result = match entry {
+                PlannedEntry::Directory { .. } => fs::create_dir_all(&local_path)
+                    .map(|_| 0)
+                    .map_err(|e| EmsError::Io(format!("无法创建本地目录: {}", e))),
+                PlannedEntry::File { source, stat, .. } => {
+                    progress.set_path(&source.to_string_lossy());
+                    download_one(&remote_fs, &source, &local_path, &stat, config.preserve_attributes, &mut progress)
+                }
+                PlannedEntry::Symlink { target, .. } => create_local_symlink(&target, &local_path).map(|_| 0),
+                PlannedEntry::Skipped { reason, .. } => {
+                    entries.push(skipped(relative, reason));
+                    continue;
+                }
+            };
+            entries.push(entry_result(relative, result));
+        }
+        progress.finish();
+
+        let result = DirectoryTransferResult::new(transfer_id, entries);
+        let log_message = format!("目录下载完成: {} -> {}, {}", config.remote_path, config.local_path, result.message);
+        add_log_internal(&app_state, if result.success { "INFO" } else { "WARN" }, &log_message);
+        println!("✅ [SFTP] {}", log_message);
+        Ok(result)
+    }).await
+}
+
+// 列出本地目录中需要传输的内容，目录在其内容之前
+fn plan_local(root: &Path, filter: &PathFilter, symlinks: SymlinkPolicy) -> EmsResult<Vec<PlannedEntry>> {
+    let mut plan = Vec::new();
+    let mut walker = WalkDir::new(root)
+        .min_depth(1)
+        .follow_links(symlinks == SymlinkPolicy::Follow)
+        .sort_by_file_name()
+        .into_iter();
+
+    while let Some(entry) = walker.next() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                let relative = e.path()
+                    .and_then(|path| path.strip_prefix(root).ok())
+                    .map(relative_string)
+                    .unwrap_or_default();
+                plan.push(PlannedEntry::Skipped { relative, reason: format!("无法读取: {}", e) });
+                continue;
+            }
+        };
+        let relative = relative_string(entry.path().strip_prefix(root).unwrap_or(entry.path()));
+
+        if filter.is_excluded(&relative) {
+            if entry.file_type().is_dir() {
+                walker.skip_current_dir();
+            }
+            continue;
+        }
+
+        let file_type = entry.file_type();
+        if file_type.is_symlink() {
+            match symlinks {
+                SymlinkPolicy::Preserve => match fs::read_link(entry.path()) {
+                    Ok(target) => plan.push(PlannedEntry::Symlink { relative, target }),
+                    Err(e) => plan.push(PlannedEntry::Skipped { relative, reason: format!("无法读取符号链接: {}", e) }),
+                },
+                // Follow 时只有失效的链接会到这里
+                _ => plan.push(PlannedEntry::Skipped { relative, reason: "符号链接".to_string() }),
+            }
+        } else if file_type.is_dir() {
+            let perm = entry.metadata().ok().and_then(|metadata| local_mode(&metadata));
+            plan.push(PlannedEntry::Directory { relative, perm });
+        } else if file_type.is_file() {
+            if !filter.includes_file(&relative) {
+                continue;
+            }
+            let metadata = entry.metadata()
+                .map_err(|e| EmsError::Io(format!("无法获取文件元数据 '{}': {}", entry.path().display(), e)))?;
+            let stat = FileStat {
+                size: Some(metadata.len()),
+                uid: None,
+                gid: None,
+                perm: local_mode(&metadata),
+                atime: None,
+                mtime: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                    .map(|duration| duration.as_secs()),
+            };
+            plan.push(PlannedEntry::File { relative, source: entry.into_path(), stat });
+        } else {
+            plan.push(PlannedEntry::Skipped { relative, reason: "不支持的文件类型".to_string() });
+        }
+    }
+    Ok(plan)
+}
+
+// 递归列出远程目录，Follow 时用真实路径记录已访问的目录，避免链接成环
+fn plan_remote(remote_fs: &RemoteFs, root: &Path, filter: &PathFilter, symlinks: SymlinkPolicy) -> EmsResult<Vec<PlannedEntry>> {
+    let mut plan = Vec::new();
+    let mut visited = HashSet::new();
+    if let Ok(real_root) = remote_fs.with(|sftp| sftp.realpath(root)) {
+        visited.insert(real_root);
+    }
+    let mut pending = vec![(root.to_path_buf(), String::new(), 0usize)];
+
+    while let Some((dir, dir_relative, depth)) = pending.pop() {
+        let mut children = match remote_fs.with(|sftp| sftp.readdir(&dir)) {
+            Ok(children) => children,
+            Err(e) => {
+                plan.push(PlannedEntry::Skipped { relative: dir_relative, reason: format!("无法读取目录: {}", e) });
+                continue;
+            }
+        };
+        children.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut subdirs = Vec::new();
+        for (path, mut stat) in children {
+            let Some(name) = path.file_name().map(|name| name.to_string_lossy().into_owned()) else {
+                continue;
+            };
+            let relative = if dir_relative.is_empty() { name } else { format!("{}/{}", dir_relative, name) };
+            if filter.is_excluded(&relative) {
+                continue;
+            }
+
+            if stat.file_type().is_symlink() {
+                match symlinks {
+                    SymlinkPolicy::Skip => {
+                        plan.push(PlannedEntry::Skipped { relative, reason: "符号链接".to_string() });
+                        continue;
+                    }
+                    SymlinkPolicy::Preserve => {
+                        match remote_fs.with(|sftp| sftp.readlink(&path)) {
+                            Ok(target) => plan.push(PlannedEntry::Symlink { relative, target }),
+                            Err(e) => plan.push(PlannedEntry::Skipped { relative, reason: format!("无法读取符号链接: {}", e) }),
+                        }
+                        continue;
+                    }
+                    SymlinkPolicy::Follow => match remote_fs.with(|sftp| sftp.stat(&path)) {
+                        Ok(target_stat) => stat = target_stat,
+                        Err(e) => {
+                            plan.push(PlannedEntry::Skipped { relative, reason: format!("符号链接已失效: {}", e) });
+                            continue;
+                        }
+                    },
+                }
+            }
+
+            if stat.is_dir() {
+                if depth + 1 > MAX_DEPTH {
+                    plan.push(PlannedEntry::Skipped { relative, reason: "目录层级过深".to_string() });
+                    continue;
+                }
+                if let Ok(real_path) = remote_fs.with(|sftp| sftp.realpath(&path)) {
+                    if !visited.insert(real_path) {
+                        plan.push(PlannedEntry::Skipped { relative, reason: "符号链接成环".to_string() });
+                        continue;
+                    }
+                }
+                plan.push(PlannedEntry::Directory { relative: relative.clone(), perm: stat.perm });
+                subdirs.push((path, relative, depth + 1));
+            } else if stat.is_file() {
+                if filter.includes_file(&relative) {
+                    plan.push(PlannedEntry::File { relative, source: path, stat });
+                }
+            } else {
+                plan.push(PlannedEntry::Skipped { relative, reason: "不支持的文件类型".to_string() });
+            }
+        }
+        // 倒序入栈，按名称顺序处理子目录
+        pending.extend(subdirs.into_iter().rev());
+    }
+    Ok(plan)
+}
+
+fn upload_one(
+    remote_fs: &RemoteFs,
+    source: &Path,
+    remote_path: &Path,
+    stat: &FileStat,
+    preserve: bool,
+    progress: &mut TransferProgress,
+) -> EmsResult<u64> {
+    let mut local_file = File::open(source)
+        .map_err(|e| EmsError::Io(format!("无法打开本地文件: {}", e)))?;
+
+    // 与下载一样先写入临时文件，中断时不会留下看起来完整的目标文件
+    let partial_path = partial_path(remote_path);
+    let result = remote_fs.create(&partial_path)
+        .map_err(|e| EmsError::SftpError(format!("无法创建远程文件: {}", e)))
+        .and_then(|mut remote_file| {
+            copy_with_progress(&mut local_file, &mut remote_file, progress)
+                .map_err(|e| EmsError::SftpError(format!("写入远程文件失败: {}", e)))
+        });
+    let bytes = match result {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let _ = remote_fs.with(|sftp| sftp.unlink(&partial_path));
+            return Err(e);
+        }
+    };
+    replace_remote_file(remote_fs, &partial_path, remote_path)?;
+
+    if preserve {
+        let attributes = FileStat {
+            size: None,
+            uid: None,
+            gid: None,
+            perm: stat.perm.map(|perm| perm & 0o7777),
+            atime: stat.mtime,
+            mtime: stat.mtime,
+        };
+        remote_fs.with(|sftp| sftp.setstat(remote_path, attributes))
+            .map_err(|e| EmsError::SftpError(format!("无法设置远程文件属性: {}", e)))?;
+    }
+    Ok(bytes)
+}
+
+fn download_one(
+    remote_fs: &RemoteFs,
+    source: &Path,
+    local_path: &Path,
+    stat: &FileStat,
+    preserve: bool,
+    progress: &mut TransferProgress,
+) -> EmsResult<u64> {
+    let mut remote_file = remote_fs.open(source)
+        .map_err(|e| EmsError::SftpError(format!("无法打开远程文件: {}", e)))?;
+    let partial_path = partial_path(local_path);
+    let result = File::create(&partial_path)
+        .map_err(|e| EmsError::Io(format!("无法创建本地文件: {}", e)))
+        .and_then(|mut local_file| {
+            copy_with_progress(&mut remote_file, &mut local_file, progress)
+                .map_err(|e| EmsError::SftpError(format!("读取远程文件失败: {}", e)))
+        });
+    let bytes = match result {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let _ = fs::remove_file(&partial_path);
+            return Err(e);
+        }
+    };
+    fs::rename(&partial_path, local_path)?;
+
+    if preserve {
+        preserve_attributes(local_path, stat)?;
+    }
+    Ok(bytes)
+}
+
+#[cfg(unix)]
+fn create_local_symlink(target: &Path, link: &Path) -> EmsResult<()> {
+    std::os::unix::fs::symlink(target, link)
+        .map_err(|e| EmsError::Io(format!("无法创建本地符号链接: {}", e)))
+}
+
+#[cfg(not(unix))]
+fn create_local_symlink(_target: &Path, _link: &Path) -> EmsResult<()> {
+    Err(EmsError::InvalidInput("当前系统不支持保留符号链接".to_string()))
+}
+
+#[cfg(unix)]
+fn local_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode())
+}
+
+#[cfg(not(unix))]
+fn local_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+fn relative_string(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn planned_path(entry: &PlannedEntry) -> &str {
+    match entry {
+        PlannedEntry::Directory { relative, .. }
+        | PlannedEntry::File { relative, .. }
+        | PlannedEntry::Symlink { relative, .. }
+        | PlannedEntry::Skipped { relative, .. } => relative,
+    }
+}
+
+fn total_size(plan: &[PlannedEntry]) -> u64 {
+    plan.iter()
+        .map(|entry| match entry {
+            PlannedEntry::File { stat, .. } => stat.size.unwrap_or(0),
+            _ => 0,
+        })
+        .sum()
+}
+
+fn entry_result(path: String, result: EmsResult<u64>) -> TransferEntry {
+    match result {
+        Ok(bytes) => TransferEntry { path, status: EntryStatus::Transferred, bytes, message: None },
+        Err(e) => failed(path, e.to_string()),
+    }
+}
+
+fn failed(path: String, message: String) -> TransferEntry {
+    TransferEntry { path, status: EntryStatus::Failed, bytes: 0, message: Some(message) }
+}
+
+fn skipped(path: String, reason: String) -> TransferEntry {
+    TransferEntry { path, status: EntryStatus::Skipped, bytes: 0, message: Some(reason) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn planned(plan: &[PlannedEntry]) -> Vec<String> {
+        plan.iter()
+            .filter(|entry| !matches!(entry, PlannedEntry::Skipped { .. }))
+            .map(|entry| planned_path(entry).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_name_and_relative_path() {
+        let filter = PathFilter::new(&patterns(&["*.conf", "bin/*"]), &patterns(&["*.bak", "logs/", "./tmp"])).unwrap();
+
+        // 不含 / 的模式匹配任意层级的文件名
+        assert!(filter.includes_file("app.conf"));
+        assert!(filter.includes_file("etc/ems/app.conf"));
+        // 含 / 的模式只匹配相对路径，* 不跨目录
+        assert!(filter.includes_file("bin/ems"));
+        assert!(!filter.includes_file("bin/sub/ems"));
+        assert!(!filter.includes_file("lib/ems.so"));
+
+        assert!(filter.is_excluded("etc/app.conf.bak"));
+        assert!(filter.is_excluded("logs"));
+        assert!(filter.is_excluded("var/logs"));
+        assert!(filter.is_excluded("tmp"));
+        assert!(!filter.is_excluded("etc/tmp"));
+    }
+
+    #[test]
+    fn empty_include_list_includes_everything() {
+        let filter = PathFilter::new(&[], &patterns(&["  ", ""])).unwrap();
+        assert!(filter.includes_file("any/file.bin"));
+        assert!(!filter.is_excluded("any/file.bin"));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(matches!(PathFilter::new(&patterns(&["[a-"]), &[]), Err(EmsError::InvalidInput(_))));
+    }
+
+    #[test]
+    fn exclude_wins_and_prunes_directories() {
+        let root = std::env::temp_dir().join(format!("ems-dir-transfer-{}", uuid::Uuid::new_v4()));
+        for dir in ["etc", "logs/old", "node_modules/pkg"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in ["etc/app.conf", "etc/app.conf.bak", "logs/old/app.conf", "node_modules/pkg/index.conf", "readme.md"] {
+            fs::write(root.join(file), b"ems").unwrap();
+        }
+
+        let filter = PathFilter::new(&patterns(&["*.conf", "*.bak"]), &patterns(&["*.bak", "logs", "node_modules"])).unwrap();
+        let plan = plan_local(&root, &filter, SymlinkPolicy::Skip);
+        fs::remove_dir_all(&root).unwrap();
+
+        // include 同时匹配时 exclude 优先；被排除的目录整个子树都不传输
+        // 目录本身不受 include 限制
+        assert_eq!(planned(&plan.unwrap()), vec!["etc", "etc/app.conf"]);
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::PoisonError;
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeStruct;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+// 所有命令统一使用的错误类型
+// 序列化为 {kind, message, details}，前端根据kind区分错误类型
+#[derive(Debug, Error)]
+pub enum EmsError {
+    #[error("没有活动的SSH连接")]
+    NotConnected,
+
+    #[error("认证失败: {0}")]
+    AuthFailed(String),
+
+    #[error("未知主机: {host}:{port} 不在known_hosts中，主机密钥指纹: {fingerprint}")]
+    UnknownHost {
+        host: String,
+        port: u16,
+        fingerprint: String,
+    },
+
+    #[error("主机密钥已变更: {host}:{port} 的主机密钥与known_hosts中记录的不一致，可能存在中间人攻击，新的主机密钥指纹: {fingerprint}")]
+    HostKeyMismatch {
+        host: String,
+        port: u16,
+        fingerprint: String,
+    },
+
+    #[error("命令执行失败，退出代码: {}，输出: {stderr}", exit_code.map(|code| code.to_string()).unwrap_or_else(|| "未知".to_string()))]
+    CommandFailed {
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+
+    #[error("校验失败: {path} 的{algorithm}校验和不一致，本地: {local}，远程: {remote}")]
+    ChecksumMismatch {
+        path: String,
+        algorithm: String,
+        local: String,
+        remote: String,
+    },
+
+    #[error("SFTP错误: {0}")]
+    SftpError(String),
+
+    #[error("解析失败: {0}")]
+    ParseError(String),
+
+    #[error("内部状态锁异常: {0}")]
+    LockPoisoned(String),
+
+    #[error("操作超时: {0}")]
+    Timeout(String),
+
+    #[error("SSH错误: {0}")]
+    Ssh(String),
+
+    #[error("文件读写错误: {0}")]
+    Io(String),
+
+    #[error("{0}")]
+    NotFound(String),
+
+    #[error("{0}")]
+    InvalidInput(String),
+
+    #[error("凭据保险库已锁定，请先输入主密码解锁")]
+    VaultLocked,
+
+    #[error("{0}")]
+    Cancelled(String),
+}
+
+pub type EmsResult<T> = Result<T, EmsError>;
+
+impl EmsError {
+    // 错误类型名称
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EmsError::NotConnected => "NotConnected",
+            EmsError::AuthFailed(_) => "AuthFailed",
+            EmsError::UnknownHost { .. } => "UnknownHost",
+            EmsError::HostKeyMismatch { .. } => "HostKeyMismatch",
+            EmsError::CommandFailed { .. } => "CommandFailed",
+            EmsError::ChecksumMismatch { .. } => "ChecksumMismatch",
+            EmsError::SftpError(_) => "SftpError",
+            EmsError::ParseError(_) => "ParseError",
+            EmsError::LockPoisoned(_) => "LockPoisoned",
+            EmsError::Timeout(_) => "Timeout",
+            EmsError::Ssh(_) => "Ssh",
+            EmsError::Io(_) => "Io",
+            EmsError::NotFound(_) => "NotFound",
+            EmsError::InvalidInput(_) => "InvalidInput",
+            EmsError::VaultLocked => "VaultLocked",
+            EmsError::Cancelled(_) => "Cancelled",
+        }
+    }
+
+    // 结构化的附加信息
+    pub fn details(&self) -> Value {
+        match self {
+            EmsError::UnknownHost { host, port, fingerprint }
+            | EmsError::HostKeyMismatch { host, port, fingerprint } => json!({
+                "host": host,
+                "port": port,
+                "fingerprint": fingerprint,
+            }),
+            EmsError::CommandFailed { exit_code, stderr } => json!({
+                "exit_code": exit_code,
+                "stderr": stderr,
+            }),
+            EmsError::ChecksumMismatch { path, algorithm, local, remote } => json!({
+                "path": path,
+                "algorithm": algorithm,
+                "local": local,
+                "remote": remote,
+            }),
+            _ => Value::Null,
+        }
+    }
+}
+
+impl Serialize for EmsError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("EmsError", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+// libssh2 的错误代码，libssh2-sys 不是直接依赖，这里单独定义
+const LIBSSH2_ERROR_TIMEOUT: i32 = -9;
+const LIBSSH2_ERROR_AUTHENTICATION_FAILED: i32 = -18;
+const LIBSSH2_ERROR_PUBLICKEY_UNVERIFIED: i32 = -19;
+
+// 认证失败和超时单独区分，前端据此提示重新输入凭据或检查网络
+impl From<ssh2::Error> for EmsError {
+    fn from(e: ssh2::Error) -> Self {
+        match e.code() {
+            ssh2::ErrorCode::Session(LIBSSH2_ERROR_AUTHENTICATION_FAILED | LIBSSH2_ERROR_PUBLICKEY_UNVERIFIED) => {
+                EmsError::AuthFailed(e.message().to_string())
+            }
+            ssh2::ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT) => EmsError::Timeout(e.message().to_string()),
+            _ => EmsError::Ssh(e.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for EmsError {
+    fn from(e: std::io::Error) -> Self {
+        EmsError::Io(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for EmsError {
+    fn from(e: serde_json::Error) -> Self {
+        EmsError::ParseError(e.to_string())
+    }
+}
+
+impl<T> From<PoisonError<T>> for EmsError {
+    fn from(e: PoisonError<T>) -> Self {
+        EmsError::LockPoisoned(e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssh2::ErrorCode;
+
+    #[test]
+    fn serializes_kind_message_and_details() {
+        let cases = [
+            (
+                EmsError::CommandFailed { exit_code: Some(1), stderr: "no such file".to_string() },
+                "CommandFailed",
+                json!({"exit_code": 1, "stderr": "no such file"}),
+            ),
+            (
+                EmsError::ChecksumMismatch {
+                    path: "/tmp/a.tar".to_string(),
+                    algorithm: "SHA-256".to_string(),
+                    local: "aa".to_string(),
+                    remote: "bb".to_string(),
+                },
+                "ChecksumMismatch",
+                json!({"path": "/tmp/a.tar", "algorithm": "SHA-256", "local": "aa", "remote": "bb"}),
+            ),
+            (EmsError::Io("磁盘已满".to_string()), "Io", Value::Null),
+        ];
+
+        for (error, kind, details) in cases {
+            let message = error.to_string();
+            let value = serde_json::to_value(&error).unwrap();
+            assert_eq!(value, json!({"kind": kind, "message": message, "details": details}));
+        }
+    }
+
+    #[test]
+    fn maps_ssh_error_codes() {
+        let auth = EmsError::from(ssh2::Error::new(ErrorCode::Session(LIBSSH2_ERROR_AUTHENTICATION_FAILED), "Authentication failed"));
+        assert!(matches!(auth, EmsError::AuthFailed(ref message) if message == "Authentication failed"));
+        let unverified = EmsError::from(ssh2::Error::new(ErrorCode::Session(LIBSSH2_ERROR_PUBLICKEY_UNVERIFIED), "Invalid signature"));
+        assert_eq!(unverified.kind(), "AuthFailed");
+        let timeout = EmsError::from(ssh2::Error::new(ErrorCode::Session(LIBSSH2_ERROR_TIMEOUT), "Timed out"));
+        assert_eq!(timeout.kind(), "Timeout");
+        let other = EmsError::from(ssh2::Error::new(ErrorCode::Session(-7), "Unable to send data"));
+        assert_eq!(other.kind(), "Ssh");
+        let sftp = EmsError::from(ssh2::Error::new(ErrorCode::SFTP(2), "no such file"));
+        assert_eq!(sftp.kind(), "Ssh");
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use ssh2::Channel;
+use tauri::{AppHandle, Manager, State};
+use serde::Serialize;
+
+use crate::AppState;
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{SharedSshSession, SshSessions, execute_command, read_available, run_blocking, with_nonblocking};
+use crate::shell::take_utf8;
+use crate::logger::add_log_internal;
+
+// 没有输出时的轮询间隔
+const EXEC_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CommandOutputEvent {
+    execution_id: String,
+    stream: &'static str,
+    data: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CommandFinishedEvent {
+    execution_id: String,
+    // completed / cancelled / timeout / failed
+    status: &'static str,
+    exit_code: Option<i32>,
+    message: Option<String>,
+}
+
+// 执行结束的原因
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StopReason {
+    Cancelled,
+    Timeout,
+    // SSH连接断开，远程进程无法再结束
+    Disconnected,
+}
+
+// 正在运行的流式命令
+pub struct RunningCommand {
+    session_id: String,
+    ssh_session: SharedSshSession,
+    channel: Mutex<Channel>,
+    // 远程shell的PID，用于取消时发送信号
+    remote_pid: Mutex<Option<u32>>,
+    stop_reason: Mutex<Option<StopReason>>,
+}
+
+impl RunningCommand {
+    fn request_stop(&self, reason: StopReason) {
+        if let Ok(mut stop_reason) = self.stop_reason.lock() {
+            stop_reason.get_or_insert(reason);
+        }
+    }
+
+    fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason.lock().ok().and_then(|reason| *reason)
+    }
+}
+
+// 所有正在运行的流式命令，按执行ID索引
+pub struct ExecSessions {
+    commands: Mutex<HashMap<String, Arc<RunningCommand>>>,
+}
+
+impl ExecSessions {
+    pub fn new() -> Self {
+        ExecSessions {
+            commands: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn get(&self, execution_id: &str) -> EmsResult<Arc<RunningCommand>> {
+        let commands = self.commands.lock()?;
+        commands
+            .get(execution_id)
+            .cloned()
+            .ok_or_else(|| EmsError::NotFound(format!("命令不存在或已结束: {}", execution_id)))
+    }
+
+    fn contains(&self, execution_id: &str) -> EmsResult<bool> {
+        Ok(self.commands.lock()?.contains_key(execution_id))
+    }
+
+    fn insert(&self, execution_id: String, command: Arc<RunningCommand>) -> EmsResult<()> {
+        let mut commands = self.commands.lock()?;
+        commands.insert(execution_id, command);
+        Ok(())
+    }
+
+    fn remove(&self, execution_id: &str) {
+        if let Ok(mut commands) = self.commands.lock() {
+            commands.remove(execution_id);
+        }
+    }
+
+    // 结束某个会话上的所有流式命令（连接断开时调用），读取线程随后发送 ssh-command-finished 事件
+    pub fn stop_session_commands(&self, session_id: &str) -> usize {
+        let Ok(commands) = self.commands.lock() else {
+            return 0;
+        };
+        let mut stopped = 0;
+        for running in commands.values().filter(|running| running.session_id == session_id) {
+            running.request_stop(StopReason::Disconnected);
+            stopped += 1;
+        }
+        stopped
+    }
+}
+
+// 初始化流式命令状态
+pub fn init_exec_state() -> ExecSessions {
+    ExecSessions::new()
+}
+
+// 流式执行命令，输出通过 ssh-command-output 事件推送，结束时发送 ssh-command-finished 事件
+// 命令返回前读取线程可能已经推送了事件，前端应先生成 execution_id 并开始监听，再调用本命令
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn execute_command_stream(
+    command: String,
+    timeout_secs: Option<u64>,
+    session_id: String,
+    // 不传时由后端生成，调用方拿到返回值前的事件无法与本次执行对应
+    execution_id: Option<String>,
+    app_handle: AppHandle,
+    ssh_state: State<'_, SshSessions>,
+    exec_state: State<'_, ExecSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<String> {
+    let execution_id = match execution_id {
+        Some(id) if id.trim().is_empty() => return Err(EmsError::InvalidInput("执行ID不能为空".to_string())),
+        Some(id) => id,
+        None => uuid::Uuid::new_v4().to_string(),
+    };
+    if exec_state.contains(&execution_id)? {
+        return Err(EmsError::InvalidInput(format!("执行ID已被使用: {}", execution_id)));
+    }
+    let ssh_session = ssh_state.get(&session_id)?;
+
+    let channel_session = ssh_session.clone();
+    let exec_command = command.clone();
+    let channel = run_blocking(move || {
+        let ssh_guard = channel_session.lock()?;
+        let session = ssh_guard.session.as_ref().ok_or(EmsError::NotConnected)?;
+
+        let mut channel = session.channel_session()
+            .map_err(|e| EmsError::Ssh(format!("无法创建会话通道: {}", e)))?;
+        // 先输出shell的PID，取消时据此结束远程进程
+        channel.exec(&format!("echo $$; {}", exec_command))
+            .map_err(|e| EmsError::Ssh(format!("执行命令失败: {}", e)))?;
+        Ok(channel)
+    }).await?;
+
+    let running = Arc::new(RunningCommand {
+        session_id: session_id.clone(),
+        ssh_session,
+        channel: Mutex::new(channel),
+        remote_pid: Mutex::new(None),
+        stop_reason: Mutex::new(None),
+    });
+    exec_state.insert(execution_id.clone(), running.clone())?;
+
+    let deadline = timeout_secs.map(|secs| Instant::now() + Duration::from_secs(secs));
+    let pump_execution_id = execution_id.clone();
+    thread::spawn(move || pump_command_output(pump_execution_id, running, deadline, app_handle));
+
+    let log_message = format!("流式执行命令: {} (执行ID: {})", command, execution_id);
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("🔧 [EXEC] {}", log_message);
+
+    Ok(execution_id)
+}
+
+// 取消正在运行的流式命令
+#[tauri::command]
+pub async fn cancel_command(
+    execution_id: String,
+    exec_state: State<'_, ExecSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    let running = exec_state.get(&execution_id)?;
+    running.request_stop(StopReason::Cancelled);
+
+    add_log_internal(&app_state, "INFO", &format!("取消命令: {}", execution_id));
+    println!("🔧 [EXEC] 取消命令: {}", execution_id);
+
+    Ok(())
+}
+
+// 结束远程进程并关闭通道
+fn terminate(running: &RunningCommand) {
+    let remote_pid = running.remote_pid.lock().ok().and_then(|pid| *pid);
+    if let Some(pid) = remote_pid {
+        // 先结束子进程，再结束shell本身
+        let kill_cmd = format!("pkill -TERM -P {} ; kill -TERM {}", pid, pid);
+        if let Err(e) = execute_command(&running.ssh_session, &kill_cmd) {
+            log::warn!("发送终止信号失败: {}", e);
+        }
+    } else {
+        // 还没有读到shell的PID（或无法解析），关闭通道后远程进程可能继续运行
+        log::warn!("未获取到远程进程PID，无法结束远程进程，只关闭命令通道");
+        println!("⚠️ [EXEC] 未获取到远程进程PID，远程命令可能仍在运行");
+    }
+
+    if let (Ok(mut channel), Ok(_ssh_guard)) = (running.channel.lock(), running.ssh_session.lock()) {
+        if let Err(e) = channel.close() {
+            log::warn!("关闭命令通道时出错: {}", e);
+        }
+    }
+}
+
+// 读取线程：推送stdout/stderr输出，处理取消与超时
+fn pump_command_output(
+    execution_id: String,
+    running: Arc<RunningCommand>,
+    deadline: Option<Instant>,
+    app_handle: AppHandle,
+) {
+    let mut buffer = [0u8; 8192];
+    let mut stdout_pending = Vec::new();
+    let mut stderr_pending = Vec::new();
+    let mut pid_parsed = false;
+    let mut failure = None;
+
+    loop {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            running.request_stop(StopReason::Timeout);
+        }
+        match running.stop_reason() {
+            // 连接已失效，不再发送终止信号或关闭通道
+            Some(StopReason::Disconnected) => {
+                failure = Some("SSH连接已断开".to_string());
+                break;
+            }
+            Some(_) => {
+                terminate(&running);
+                break;
+            }
+            None => {}
+        }
+
+        let poll_result = {
+            let channel = match running.channel.lock() {
+                Ok(channel) => channel,
+                Err(_) => break,
+            };
+            with_nonblocking(&running.ssh_session, || {
+                let stdout = read_available(&mut channel.stream(0), &mut buffer, &mut stdout_pending);
+                let stderr = read_available(&mut channel.stderr(), &mut buffer, &mut stderr_pending);
+                (stdout, stderr, channel.eof())
+            })
+        };
+
+        let (stdout, stderr, eof) = match poll_result {
+            Ok(result) => result,
+            Err(e) => {
+                failure = Some(e.to_string());
+                break;
+            }
+        };
+        if let Err(e) = stdout.and(stderr) {
+            failure = Some(format!("读取命令输出失败: {}", e));
+            break;
+        }
+
+        if !pid_parsed {
+            if let Some(pos) = stdout_pending.iter().position(|&b| b == b'\n') {
+                let pid_line: Vec<u8> = stdout_pending.drain(..=pos).collect();
+                let pid = String::from_utf8_lossy(&pid_line).trim().parse::<u32>().ok();
+                if let Ok(mut remote_pid) = running.remote_pid.lock() {
+                    *remote_pid = pid;
+                }
+                pid_parsed = true;
+            }
+        }
+
+        let mut has_output = false;
+        if pid_parsed {
+            has_output |= emit_chunk(&app_handle, &execution_id, "stdout", &mut stdout_pending);
+        }
+        has_output |= emit_chunk(&app_handle, &execution_id, "stderr", &mut stderr_pending);
+
+        if eof {
+            break;
+        }
+        if !has_output {
+            thread::sleep(EXEC_POLL_INTERVAL);
+        }
+    }
+
+    let stop_reason = running.stop_reason();
+    let exit_code = if stop_reason.is_none() && failure.is_none() {
+        running.channel.lock().ok().and_then(|mut channel| {
+            let _ssh_guard = running.ssh_session.lock().ok()?;
+            if let Err(e) = channel.wait_close() {
+                log::warn!("等待通道关闭时出错: {}", e);
+            }
+            channel.exit_status().ok()
+        })
+    } else {
+        None
+    };
+
+    if let Some(exec_state) = app_handle.try_state::<ExecSessions>() {
+        exec_state.remove(&execution_id);
+    }
+
+    let status = match (stop_reason, &failure) {
+        (Some(StopReason::Cancelled), _) => "cancelled",
+        (Some(StopReason::Timeout), _) => "timeout",
+        (Some(StopReason::Disconnected), _) | (None, Some(_)) => "failed",
+        (None, None) => "completed",
+    };
+    println!("🔧 [EXEC] 命令结束: {} ({})", execution_id, status);
+
+    let _ = app_handle.emit_all("ssh-command-finished", CommandFinishedEvent {
+        execution_id,
+        status,
+        exit_code,
+        message: failure,
+    });
+}
+
+// 推送一段输出，返回是否有数据
+fn emit_chunk(app_handle: &AppHandle, execution_id: &str, stream: &'static str, pending: &mut Vec<u8>) -> bool {
+    let data = take_utf8(pending);
+    if data.is_empty() {
+        return false;
+    }
+
+    let _ = app_handle.emit_all("ssh-command-output", CommandOutputEvent {
+        execution_id: execution_id.to_string(),
+        stream,
+        data,
+    });
+    true
+}
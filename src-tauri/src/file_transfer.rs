@@ -1,48 +1,242 @@
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use std::sync::{Arc, Mutex};
-use tauri::State;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use ssh2::{FileStat, OpenFlags, OpenType};
+use tauri::{AppHandle, Manager, State};
 use serde::{Deserialize, Serialize};
 
-use crate::{AppState, FileTransferConfig};
-use crate::ssh::SshSession;
+use crate::{AppState, FileDownloadConfig, FileTransferConfig, SshConnectionConfig};
+use crate::error::{EmsError, EmsResult};
+use crate::checksum::{ChecksumVerification, verify_checksum};
+use crate::ssh::{SharedSshSession, SshSessions, run_blocking};
+use crate::sftp::{Locked, RemoteFs, SftpOps, open_sftp};
 use crate::logger::add_log_internal;
+use crate::transfer_journal::{JournalEntry, TransferJournal, now, verified_offset};
+
+// 每次读写的块大小
+const TRANSFER_CHUNK_SIZE: usize = 256 * 1024;
+// 新建远程目录的默认权限
+pub const DEFAULT_DIR_MODE: i32 = 0o755;
+// 进度事件的最小间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+// 传输日志的记录间隔
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileUploadResult {
     success: bool,
     message: String,
     bytes_transferred: usize,
+    // 本地文件大小
+    file_size: u64,
+    // 续传的起始偏移量，从头上传时为0
+    resumed_from: u64,
+    // 开启校验时的校验结果
+    checksum: Option<ChecksumVerification>,
+    transfer_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransferDirection {
+    Upload,
+    Download,
+}
+
+// 推送给前端的传输进度，按 transfer_id 区分
+#[derive(Debug, Clone, Serialize)]
+struct TransferProgressEvent {
+    transfer_id: String,
+    direction: TransferDirection,
+    path: String,
+    bytes_done: u64,
+    total_bytes: u64,
+    // 平均速率（字节/秒）
+    rate: f64,
+    // 预计剩余时间（秒），速率未知时为空
+    eta_secs: Option<f64>,
+    finished: bool,
+}
+
+// 统计已传输的字节数，按固定间隔推送 file-transfer-progress 事件
+pub struct TransferProgress<'a> {
+    app_handle: &'a AppHandle,
+    transfer_id: String,
+    direction: TransferDirection,
+    path: String,
+    total_bytes: u64,
+    bytes_done: u64,
+    // 续传时已有的字节数，不计入速率
+    resumed_bytes: u64,
+    started: Instant,
+    last_emit: Instant,
+    // 定期把进度写入传输日志
+    journal: Option<&'a TransferJournal>,
+    last_checkpoint: Instant,
 }
 
+impl<'a> TransferProgress<'a> {
+    pub fn new(app_handle: &'a AppHandle, transfer_id: &str, direction: TransferDirection, path: &str, total_bytes: u64) -> Self {
+        let now = Instant::now();
+        TransferProgress {
+            app_handle,
+            transfer_id: transfer_id.to_string(),
+            direction,
+            path: path.to_string(),
+            total_bytes,
+            bytes_done: 0,
+            resumed_bytes: 0,
+            started: now,
+            last_emit: now,
+            journal: None,
+            last_checkpoint: now,
+        }
+    }
+
+    pub fn with_journal(mut self, journal: &'a TransferJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    // 从已传输的偏移量继续
+    pub fn resume_from(&mut self, offset: u64) {
+        self.bytes_done = offset;
+        self.resumed_bytes = offset;
+    }
+
+    pub fn bytes_done(&self) -> u64 {
+        self.bytes_done
+    }
+
+    // 立即写入传输日志，传输失败时调用
+    pub fn checkpoint(&mut self) {
+        self.last_checkpoint = Instant::now();
+        if let Some(journal) = self.journal {
+            if let Err(e) = journal.checkpoint(&self.transfer_id, self.bytes_done) {
+                println!("⚠️ [TRANSFER] 写入传输日志失败: {}", e);
+            }
+        }
+    }
+
+    // 目录传输时切换到下一个文件
+    pub fn set_path(&mut self, path: &str) {
+        self.path = path.to_string();
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.bytes_done += bytes;
+        if self.last_emit.elapsed() >= PROGRESS_INTERVAL {
+            self.emit(false);
+        }
+        if self.journal.is_some() && self.last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+            self.checkpoint();
+        }
+    }
+
+    pub fn finish(&mut self) {
+        self.emit(true);
+    }
+
+    fn emit(&mut self, finished: bool) {
+        self.last_emit = Instant::now();
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let transferred = self.bytes_done - self.resumed_bytes;
+        let rate = if elapsed > 0.0 { transferred as f64 / elapsed } else { 0.0 };
+        let eta_secs = (rate > 0.0).then(|| self.total_bytes.saturating_sub(self.bytes_done) as f64 / rate);
+
+        let _ = self.app_handle.emit_all("file-transfer-progress", TransferProgressEvent {
+            transfer_id: self.transfer_id.clone(),
+            direction: self.direction,
+            path: self.path.clone(),
+            bytes_done: self.bytes_done,
+            total_bytes: self.total_bytes,
+            rate,
+            eta_secs,
+            finished,
+        });
+    }
+}
+
+// 按固定大小的块复制，每块之后更新进度
+pub fn copy_with_progress(reader: &mut impl Read, writer: &mut impl Write, progress: &mut TransferProgress) -> io::Result<u64> {
+    let mut buffer = vec![0u8; TRANSFER_CHUNK_SIZE];
+    let mut total = 0u64;
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buffer[..n])?;
+        total += n as u64;
+        progress.advance(n as u64);
+    }
+    writer.flush()?;
+    Ok(total)
+}
+
+fn journal_entry(
+    transfer_id: &str,
+    direction: TransferDirection,
+    connection: &SshConnectionConfig,
+    local_path: &str,
+    remote_path: &str,
+    total_bytes: u64,
+    source_mtime: Option<u64>,
+) -> JournalEntry {
+    let timestamp = now();
+    JournalEntry {
+        transfer_id: transfer_id.to_string(),
+        direction,
+        host: connection.host.clone(),
+        port: connection.port,
+        username: connection.username.clone(),
+        local_path: local_path.to_string(),
+        remote_path: remote_path.to_string(),
+        total_bytes,
+        source_mtime,
+        bytes_done: 0,
+        started_at: timestamp.clone(),
+        updated_at: timestamp,
+    }
+}
+
+// 上传文件，transfer_id 用于区分进度事件，不指定时自动生成
+// 同一文件之前的上传中断时，从远程临时文件的末尾续传
 #[tauri::command]
 pub async fn upload_file(
     config: FileTransferConfig,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    transfer_id: Option<String>,
+    app_handle: AppHandle,
+    ssh_state: State<'_, SshSessions>,
+    journal: State<'_, TransferJournal>,
     app_state: State<'_, AppState>,
-) -> Result<FileUploadResult, String> {
-    // 记录开始上传的日志
-    let log_message = format!("开始上传文件: {} -> {}", config.local_path, config.remote_path);
-    add_log_internal(&app_state, "INFO", &log_message);
-    
-    let ssh_state = ssh_state.lock().map_err(|_| "Failed to lock SSH state".to_string())?;
-    
-    if let Some(ref session) = ssh_state.session {
+) -> EmsResult<FileUploadResult> {
+    let ssh_session = ssh_state.get(&session_id)?;
+    let journal = journal.inner().clone();
+    let app_state = app_state.inner().clone();
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    run_blocking(move || {
+        // 记录开始上传的日志
+        let log_message = format!("开始上传文件: {} -> {}", config.local_path, config.remote_path);
+        add_log_internal(&app_state, "INFO", &log_message);
+
         // 打开本地文件
         let mut local_file = File::open(&config.local_path)
-            .map_err(|e| format!("无法打开本地文件: {}", e))?;
-            
-        // 获取文件大小
-        let _file_size = local_file.metadata()
-            .map_err(|e| format!("无法获取文件元数据: {}", e))?
-            .len() as usize;
-            
-        // 读取文件内容
-        let mut buffer = Vec::new();
-        local_file.read_to_end(&mut buffer)
-            .map_err(|e| format!("读取文件失败: {}", e))?;
-            
+            .map_err(|e| EmsError::Io(format!("无法打开本地文件: {}", e)))?;
+
+        // 获取文件大小和修改时间
+        let metadata = local_file.metadata()
+            .map_err(|e| EmsError::Io(format!("无法获取文件元数据: {}", e)))?;
+        let file_size = metadata.len();
+        let local_mtime = metadata.modified().ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs());
+
         // 确保远程目录存在
         let remote_path_str = config.remote_path.clone();
         let log_message = format!("准备上传到远程路径: {}", remote_path_str);
@@ -62,119 +256,485 @@ pub async fn upload_file(
             }
         };
 
-        // 创建目标目录
-        let mkdir_cmd = format!("mkdir -p '{}'", target_dir.trim_end_matches('/'));
+        // 创建SFTP会话，之后每次SFTP操作才获取会话锁，上传期间同一连接上的其他操作不受影响
+        add_log_internal(&app_state, "INFO", "创建SFTP会话...");
+        let remote_fs = open_sftp(&ssh_session)
+            .inspect_err(|e| add_log_internal(&app_state, "ERROR", &e.to_string()))?;
+        add_log_internal(&app_state, "INFO", "SFTP会话创建成功");
+
+        // 通过SFTP创建目标目录
         let log_message = format!("创建远程目录: {}", target_dir);
         add_log_internal(&app_state, "INFO", &log_message);
-
-        let mut channel = session.channel_session()
-            .map_err(|e| format!("无法创建会话通道: {}", e))?;
-
-        channel.exec(&mkdir_cmd)
-            .map_err(|e| format!("创建远程目录失败: {}", e))?;
-
-        // 读取命令输出以检查是否成功
-        let mut output = String::new();
-        if let Err(e) = channel.read_to_string(&mut output) {
-            log::warn!("读取mkdir命令输出失败: {}", e);
-        }
-
-        // 发送EOF信号
-        channel.send_eof()
-            .map_err(|e| format!("发送EOF失败: {}", e))?;
-
-        // 等待通道关闭，但添加错误处理
-        if let Err(e) = channel.wait_close() {
-            log::warn!("文件传输中等待通道关闭时出错: {}", e);
-            // 不返回错误，继续执行文件传输
-        }
-
-        let exit_status = channel.exit_status().unwrap_or(-1);
-        if exit_status != 0 && exit_status != -1 {
-            let error_msg = format!("创建远程目录失败，退出代码: {}，输出: {}", exit_status, output);
-            add_log_internal(&app_state, "ERROR", &error_msg);
-            return Err(error_msg);
-        }
+        create_remote_dir_all(&remote_fs, Path::new(target_dir.trim_end_matches('/')), DEFAULT_DIR_MODE)
+            .inspect_err(|e| add_log_internal(&app_state, "ERROR", &format!("创建远程目录失败: {}", e)))?;
 
         let success_msg = format!("远程目录创建成功: {}", target_dir);
         add_log_internal(&app_state, "INFO", &success_msg);
-        
-        // 创建SFTP会话
-        add_log_internal(&app_state, "INFO", "创建SFTP会话...");
-        let sftp = session.sftp()
-            .map_err(|e| {
-                let error_msg = format!("无法创建SFTP会话: {}", e);
-                add_log_internal(&app_state, "ERROR", &error_msg);
-                error_msg
-            })?;
-        add_log_internal(&app_state, "INFO", "SFTP会话创建成功");
 
-        // 检查远程路径是否为目录
-        let remote_path_str = config.remote_path.clone();
-        if remote_path_str.ends_with('/') {
-            // 如果路径以/结尾，说明是目录，需要添加文件名
+        // 路径以/结尾说明是目录，需要添加文件名
+        let full_remote_path = if remote_path_str.ends_with('/') {
             let file_name = Path::new(&config.local_path)
                 .file_name()
                 .and_then(|name| name.to_str())
-                .ok_or_else(|| "无法获取本地文件名".to_string())?;
-            let full_remote_path = format!("{}{}", remote_path_str, file_name);
-            let remote_path = Path::new(&full_remote_path);
+                .ok_or_else(|| EmsError::InvalidInput("无法获取本地文件名".to_string()))?;
+            format!("{}{}", remote_path_str, file_name)
+        } else {
+            remote_path_str
+        };
 
-            let log_message = format!("创建远程文件: {}", full_remote_path);
-            add_log_internal(&app_state, "INFO", &log_message);
+        // 先上传到 .part 临时文件，完成后再重命名；连接中断后可以从临时文件的末尾续传
+        let entry = {
+            let ssh_guard = ssh_session.lock()?;
+            let connection = ssh_guard.config.as_ref().ok_or(EmsError::NotConnected)?;
+            journal_entry(&transfer_id, TransferDirection::Upload, connection, &config.local_path, &full_remote_path, file_size, local_mtime)
+        };
+        let partial_remote_path = partial_path(Path::new(&full_remote_path));
 
-            // 创建远程文件
-            let mut remote_file = sftp.create(remote_path)
-                .map_err(|e| {
-                    let error_msg = format!("无法创建远程文件 '{}': {} (错误代码: {:?})", full_remote_path, e, e);
-                    add_log_internal(&app_state, "ERROR", &error_msg);
-                    error_msg
-                })?;
+        let resume_offset = match journal.find_resumable(&entry)? {
+            Some(previous) => {
+                let partial_len = remote_fs.with(|sftp| sftp.stat(&partial_remote_path)).ok().and_then(|stat| stat.size).unwrap_or(0);
+                let offset = match remote_fs.open(&partial_remote_path) {
+                    Ok(mut partial) => verified_offset(&mut local_file, &mut partial, partial_len, file_size),
+                    Err(_) => 0,
+                };
+                journal.begin(JournalEntry { started_at: previous.started_at, bytes_done: offset, ..entry })?;
+                offset
+            }
+            None => {
+                journal.begin(entry)?;
+                0
+            }
+        };
 
-            // 写入文件内容
-            remote_file.write_all(&buffer)
-                .map_err(|e| {
-                    let error_msg = format!("写入远程文件失败: {}", e);
-                    add_log_internal(&app_state, "ERROR", &error_msg);
-                    error_msg
-                })?;
-        } else {
-            // 直接使用指定的路径
-            let log_message = format!("创建远程文件: {}", remote_path_str);
+        let mut remote_file = if resume_offset > 0 {
+            let log_message = format!("从 {} 字节处续传: {}", resume_offset, full_remote_path);
             add_log_internal(&app_state, "INFO", &log_message);
+            println!("🔄 [SFTP] {}", log_message);
 
-            let remote_path = Path::new(&remote_path_str);
+            let mut remote_file = remote_fs.open_mode(&partial_remote_path, OpenFlags::WRITE, 0o644, OpenType::File)
+                .map_err(|e| EmsError::SftpError(format!("无法打开远程临时文件 '{}': {}", partial_remote_path.display(), e)))?;
+            remote_file.seek(SeekFrom::Start(resume_offset))?;
+            local_file.seek(SeekFrom::Start(resume_offset))?;
+            remote_file
+        } else {
+            let log_message = format!("创建远程文件: {}", full_remote_path);
+            add_log_internal(&app_state, "INFO", &log_message);
 
-            // 创建远程文件
-            let mut remote_file = sftp.create(remote_path)
+            local_file.seek(SeekFrom::Start(0))?;
+            remote_fs.create(&partial_remote_path)
                 .map_err(|e| {
-                    let error_msg = format!("无法创建远程文件 '{}': {} (错误代码: {:?})", remote_path_str, e, e);
+                    let error_msg = format!("无法创建远程文件 '{}': {} (错误代码: {:?})", partial_remote_path.display(), e, e);
                     add_log_internal(&app_state, "ERROR", &error_msg);
-                    error_msg
-                })?;
+                    EmsError::SftpError(error_msg)
+                })?
+        };
 
-            // 写入文件内容
-            remote_file.write_all(&buffer)
-                .map_err(|e| {
-                    let error_msg = format!("写入远程文件失败: {}", e);
-                    add_log_internal(&app_state, "ERROR", &error_msg);
-                    error_msg
-                })?;
-        }
+        // 分块写入文件内容，不把整个文件读入内存
+        let mut progress = TransferProgress::new(&app_handle, &transfer_id, TransferDirection::Upload, &full_remote_path, file_size)
+            .with_journal(&journal);
+        progress.resume_from(resume_offset);
+        let bytes_transferred = copy_with_progress(&mut local_file, &mut remote_file, &mut progress)
+            .map_err(|e| {
+                progress.checkpoint();
+                let error_msg = format!("写入远程文件失败，重新上传时将从 {} 字节处续传: {}", progress.bytes_done(), e);
+                add_log_internal(&app_state, "ERROR", &error_msg);
+                EmsError::SftpError(error_msg)
+            })?;
+        drop(remote_file);
+
+        // 先校验临时文件，通过后再替换目标文件，校验失败时已有的目标文件不受影响
+        let checksum = if config.verify_checksum {
+            let verified = verify_transfer(&ssh_session, Path::new(&config.local_path), &partial_remote_path.to_string_lossy(), &full_remote_path, &app_state);
+            if let Err(EmsError::ChecksumMismatch { .. }) = verified {
+                // 临时文件内容有误，不能用于续传
+                let _ = remote_fs.with(|sftp| sftp.unlink(&partial_remote_path));
+                journal.remove(&transfer_id)?;
+            }
+            Some(verified?)
+        } else {
+            None
+        };
+
+        // 替换已存在的目标文件
+        let final_remote_path = Path::new(&full_remote_path);
+        replace_remote_file(&remote_fs, &partial_remote_path, final_remote_path)?;
+        journal.remove(&transfer_id)?;
+        progress.finish();
 
         // 记录成功上传的日志
         let success_message = format!("文件上传成功: {} -> {}, 大小: {} 字节", 
-            config.local_path, config.remote_path, buffer.len());
+            config.local_path, full_remote_path, file_size);
         add_log_internal(&app_state, "INFO", &success_message);
-        
+
         Ok(FileUploadResult {
             success: true,
             message: "文件上传成功".to_string(),
-            bytes_transferred: buffer.len(),
+            bytes_transferred: bytes_transferred as usize,
+            file_size,
+            resumed_from: resume_offset,
+            checksum,
+            transfer_id,
+        })
+    }).await
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileDownloadResult {
+    success: bool,
+    message: String,
+    bytes_transferred: usize,
+    // 远程文件大小
+    file_size: u64,
+    // 续传的起始偏移量，从头下载时为0
+    resumed_from: u64,
+    // 开启校验时的校验结果
+    checksum: Option<ChecksumVerification>,
+    // 实际保存的本地路径
+    local_path: String,
+    transfer_id: String,
+}
+
+// 通过SFTP下载远程文件，按块写入本地，不会把整个文件读入内存
+// 同一文件之前的下载中断时，从本地临时文件的末尾续传
+#[tauri::command]
+pub async fn download_file(
+    config: FileDownloadConfig,
+    session_id: String,
+    transfer_id: Option<String>,
+    app_handle: AppHandle,
+    ssh_state: State<'_, SshSessions>,
+    journal: State<'_, TransferJournal>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<FileDownloadResult> {
+    let ssh_session = ssh_state.get(&session_id)?;
+    let journal = journal.inner().clone();
+    let app_state = app_state.inner().clone();
+    let transfer_id = transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    run_blocking(move || {
+        let log_message = format!("开始下载文件: {} -> {}", config.remote_path, config.local_path);
+        add_log_internal(&app_state, "INFO", &log_message);
+        println!("🔧 [SFTP] {}", log_message);
+
+        // 每次SFTP操作才获取会话锁，下载期间同一连接上的其他操作不受影响
+        let remote_fs = open_sftp(&ssh_session)?;
+
+        let remote_path = Path::new(&config.remote_path);
+        let remote_stat = remote_fs.with(|sftp| sftp.stat(remote_path))
+            .map_err(|e| EmsError::SftpError(format!("无法访问远程文件 '{}': {}", config.remote_path, e)))?;
+        if remote_stat.is_dir() {
+            return Err(EmsError::InvalidInput(format!("远程路径是目录: {}", config.remote_path)));
+        }
+
+        // 本地路径是已存在的目录时，使用远程文件名
+        let mut local_path = PathBuf::from(&config.local_path);
+        if local_path.is_dir() {
+            let file_name = remote_path
+                .file_name()
+                .ok_or_else(|| EmsError::InvalidInput("无法获取远程文件名".to_string()))?;
+            local_path.push(file_name);
+        }
+
+        let mut remote_file = remote_fs.open(remote_path)
+            .map_err(|e| EmsError::SftpError(format!("无法打开远程文件 '{}': {}", config.remote_path, e)))?;
+
+        // 先写入临时文件，下载完成后再重命名，避免中断时留下不完整的目标文件
+        // 同一文件之前的下载中断时，从本地临时文件的末尾续传
+        let partial_path = partial_path(&local_path);
+        let file_size = remote_stat.size.unwrap_or(0);
+        let local_path_str = local_path.to_string_lossy().into_owned();
+        let entry = {
+            let ssh_guard = ssh_session.lock()?;
+            let connection = ssh_guard.config.as_ref().ok_or(EmsError::NotConnected)?;
+            journal_entry(&transfer_id, TransferDirection::Download, connection, &local_path_str, &config.remote_path, file_size, remote_stat.mtime)
+        };
+
+        let resume_offset = match journal.find_resumable(&entry)? {
+            Some(previous) => {
+                let partial_len = fs::metadata(&partial_path).map(|metadata| metadata.len()).unwrap_or(0);
+                let offset = match File::open(&partial_path) {
+                    Ok(mut partial) => verified_offset(&mut remote_file, &mut partial, partial_len, file_size),
+                    Err(_) => 0,
+                };
+                journal.begin(JournalEntry { started_at: previous.started_at, bytes_done: offset, ..entry })?;
+                offset
+            }
+            None => {
+                journal.begin(entry)?;
+                0
+            }
+        };
+
+        let mut local_file = if resume_offset > 0 {
+            let log_message = format!("从 {} 字节处续传: {}", resume_offset, config.remote_path);
+            add_log_internal(&app_state, "INFO", &log_message);
+            println!("🔄 [SFTP] {}", log_message);
+
+            let mut local_file = File::options().write(true).open(&partial_path)?;
+            local_file.set_len(resume_offset)?;
+            local_file.seek(SeekFrom::Start(resume_offset))?;
+            remote_file.seek(SeekFrom::Start(resume_offset))?;
+            local_file
+        } else {
+            remote_file.seek(SeekFrom::Start(0))?;
+            File::create(&partial_path)
+                .map_err(|e| EmsError::Io(format!("无法创建本地文件 '{}': {}", partial_path.display(), e)))?
+        };
+
+        let mut progress = TransferProgress::new(&app_handle, &transfer_id, TransferDirection::Download, &config.remote_path, file_size)
+            .with_journal(&journal);
+        progress.resume_from(resume_offset);
+        let bytes_transferred = copy_with_progress(&mut remote_file, &mut local_file, &mut progress)
+            .map_err(|e| {
+                progress.checkpoint();
+                let error_msg = format!("读取远程文件失败，重新下载时将从 {} 字节处续传: {}", progress.bytes_done(), e);
+                add_log_internal(&app_state, "ERROR", &error_msg);
+                EmsError::SftpError(error_msg)
+            })?;
+        drop(local_file);
+
+        // 先校验临时文件，通过后再替换目标文件，校验失败时已有的目标文件不受影响
+        let checksum = if config.verify_checksum {
+            let verified = verify_transfer(&ssh_session, &partial_path, &config.remote_path, &config.remote_path, &app_state);
+            if let Err(EmsError::ChecksumMismatch { .. }) = verified {
+                // 临时文件内容有误，不能用于续传
+                let _ = fs::remove_file(&partial_path);
+                journal.remove(&transfer_id)?;
+            }
+            Some(verified?)
+        } else {
+            None
+        };
+
+        journal.remove(&transfer_id)?;
+        progress.finish();
+        fs::rename(&partial_path, &local_path)
+            .map_err(|e| EmsError::Io(format!("无法保存本地文件 '{}': {}", local_path.display(), e)))?;
+
+        if config.preserve_attributes {
+            if let Err(e) = preserve_attributes(&local_path, &remote_stat) {
+                let warn_msg = format!("无法保留文件属性 '{}': {}", local_path.display(), e);
+                add_log_internal(&app_state, "WARN", &warn_msg);
+                println!("⚠️ [SFTP] {}", warn_msg);
+            }
+        }
+
+        let success_message = format!("文件下载成功: {} -> {}, 大小: {} 字节",
+            config.remote_path, local_path.display(), file_size);
+        add_log_internal(&app_state, "INFO", &success_message);
+        println!("✅ [SFTP] {}", success_message);
+
+        Ok(FileDownloadResult {
+            success: true,
+            message: "文件下载成功".to_string(),
+            bytes_transferred: bytes_transferred as usize,
+            file_size,
+            resumed_from: resume_offset,
+            checksum,
+            local_path: local_path_str,
+            transfer_id,
+        })
+    }).await
+}
+
+// 比对本地和远程文件的校验和并记录日志，不一致时返回 ChecksumMismatch
+// local_path/remote_path 为实际计算校验和的文件（可以是临时文件），target 为日志中显示的目标路径
+fn verify_transfer(
+    ssh_session: &SharedSshSession,
+    local_path: &Path,
+    remote_path: &str,
+    target: &str,
+    app_state: &AppState,
+) -> EmsResult<ChecksumVerification> {
+    let verification = verify_checksum(ssh_session, local_path, remote_path)
+        .inspect_err(|e| add_log_internal(app_state, "ERROR", &format!("无法校验文件 '{}': {}", target, e)))?;
+
+    if !verification.matched() {
+        let error = verification.to_error(target);
+        add_log_internal(app_state, "ERROR", &error.to_string());
+        println!("❌ [SFTP] {}", error);
+        return Err(error);
+    }
+
+    let log_message = format!("{}校验通过: {} ({})", verification.algorithm.name(), target, verification.local);
+    add_log_internal(app_state, "INFO", &log_message);
+    println!("✅ [SFTP] {}", log_message);
+    Ok(verification)
+}
+
+pub fn partial_path(local_path: &Path) -> PathBuf {
+    let mut file_name = local_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".part");
+    local_path.with_file_name(file_name)
+}
+
+// 逐级创建远程目录，已存在的目录跳过
+pub fn create_remote_dir_all(remote_fs: &RemoteFs, path: &Path, mode: i32) -> EmsResult<()> {
+    let mut current = PathBuf::new();
+    for component in path.components() {
+        current.push(component);
+        match remote_fs.with(|sftp| sftp.stat(&current)) {
+            Ok(stat) if stat.is_dir() => continue,
+            Ok(_) => {
+                return Err(EmsError::SftpError(format!("远程路径已存在且不是目录: {}", current.display())));
+            }
+            Err(_) => remote_fs.with(|sftp| sftp.mkdir(&current, mode))
+                .map_err(|e| EmsError::SftpError(format!("无法创建远程目录 '{}': {}", current.display(), e)))?,
+        }
+    }
+    Ok(())
+}
+
+// 用临时文件替换目标文件，覆盖时沿用原文件的权限和属主（例如可执行文件的执行权限）
+// 先直接重命名，OpenSSH 的 rename 不会覆盖已存在的文件，失败时再删除目标后重命名
+pub fn replace_remote_file<T: SftpOps, S>(remote_fs: &Locked<T, S>, partial_path: &Path, target: &Path) -> EmsResult<()> {
+    remote_fs
+        .with(|sftp| {
+            let Ok(existing) = sftp.stat(target) else {
+                return sftp.rename(partial_path, target);
+            };
+            keep_attributes(sftp, partial_path, &existing);
+            sftp.rename(partial_path, target).or_else(|e| {
+                // 临时文件不存在等其他原因导致的失败不能删除目标
+                sftp.stat(partial_path).map_err(|_| e)?;
+                sftp.unlink(target)?;
+                sftp.rename(partial_path, target)
+            })
         })
-    } else {
-        let error_msg = "没有活动的SSH连接";
-        add_log_internal(&app_state, "ERROR", error_msg);
-        Err(error_msg.to_string())
+        .map_err(|e| EmsError::SftpError(format!("无法重命名远程文件 '{}': {}", target.display(), e)))
+}
+
+// 只有root可以修改属主，失败时只保留权限
+fn keep_attributes<T: SftpOps>(sftp: &T, path: &Path, existing: &FileStat) {
+    let attributes = FileStat {
+        size: None,
+        uid: existing.uid,
+        gid: existing.gid,
+        perm: existing.perm.map(|perm| perm & 0o7777),
+        atime: None,
+        mtime: None,
+    };
+    if sftp.setstat(path, attributes.clone()).is_err() {
+        let perm_only = FileStat { uid: None, gid: None, ..attributes };
+        if let Err(e) = sftp.setstat(path, perm_only) {
+            println!("⚠️ [SFTP] 无法保留 {} 的原有权限: {}", path.display(), e);
+        }
+    }
+}
+
+// 把远程文件的修改时间和权限应用到本地文件
+pub fn preserve_attributes(local_path: &Path, remote_stat: &FileStat) -> EmsResult<()> {
+    if let Some(mtime) = remote_stat.mtime {
+        let file = File::options().write(true).open(local_path)?;
+        file.set_modified(UNIX_EPOCH + Duration::from_secs(mtime))?;
     }
-} 
\ No newline at end of file
+
+    #[cfg(unix)]
+    if let Some(perm) = remote_stat.perm {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(local_path, fs::Permissions::from_mode(perm & 0o7777))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use ssh2::ErrorCode;
+
+    // 内存中的远程文件系统：记录每个文件的属性，与 OpenSSH 一样 rename 不覆盖已存在的文件
+    // 以 size 区分文件内容，非root用户不能修改属主
+    struct MemoryFs {
+        files: RefCell<HashMap<PathBuf, FileStat>>,
+        root: bool,
+    }
+
+    fn failure() -> ssh2::Error {
+        ssh2::Error::new(ErrorCode::Session(-31), "SFTP操作失败")
+    }
+
+    impl SftpOps for MemoryFs {
+        fn stat(&self, path: &Path) -> Result<FileStat, ssh2::Error> {
+            self.files.borrow().get(path).cloned().ok_or_else(failure)
+        }
+
+        fn setstat(&self, path: &Path, stat: FileStat) -> Result<(), ssh2::Error> {
+            let mut files = self.files.borrow_mut();
+            let file = files.get_mut(path).ok_or_else(failure)?;
+            let chown = stat.uid.is_some_and(|uid| Some(uid) != file.uid) || stat.gid.is_some_and(|gid| Some(gid) != file.gid);
+            if chown && !self.root {
+                return Err(failure());
+            }
+            file.uid = stat.uid.or(file.uid);
+            file.gid = stat.gid.or(file.gid);
+            file.perm = stat.perm.or(file.perm);
+            Ok(())
+        }
+
+        fn rename(&self, src: &Path, dst: &Path) -> Result<(), ssh2::Error> {
+            let mut files = self.files.borrow_mut();
+            if files.contains_key(dst) {
+                return Err(failure());
+            }
+            let file = files.remove(src).ok_or_else(failure)?;
+            files.insert(dst.to_path_buf(), file);
+            Ok(())
+        }
+
+        fn unlink(&self, path: &Path) -> Result<(), ssh2::Error> {
+            self.files.borrow_mut().remove(path).map(|_| ()).ok_or_else(failure)
+        }
+    }
+
+    fn file(size: u64, perm: u32, owner: u32) -> FileStat {
+        FileStat { size: Some(size), uid: Some(owner), gid: Some(owner), perm: Some(perm), atime: None, mtime: None }
+    }
+
+    fn memory_fs(root: bool, files: &[(&str, FileStat)]) -> Locked<MemoryFs, ()> {
+        let files = files.iter().map(|(path, stat)| (PathBuf::from(path), stat.clone())).collect();
+        Locked::new(Arc::new(Mutex::new(())), MemoryFs { files: RefCell::new(files), root })
+    }
+
+    fn stat_of(remote_fs: &Locked<MemoryFs, ()>, path: &str) -> Option<FileStat> {
+        remote_fs.with(|fs| fs.files.borrow().get(Path::new(path)).cloned())
+    }
+
+    #[test]
+    fn keeps_mode_and_owner_when_overwriting() {
+        let remote_fs = memory_fs(true, &[
+            ("/ems/app", file(10, 0o100755, 1000)),
+            ("/ems/app.part", file(20, 0o100644, 0)),
+        ]);
+        replace_remote_file(&remote_fs, Path::new("/ems/app.part"), Path::new("/ems/app")).unwrap();
+
+        assert_eq!(stat_of(&remote_fs, "/ems/app"), Some(file(20, 0o755, 1000)));
+        assert_eq!(stat_of(&remote_fs, "/ems/app.part"), None);
+    }
+
+    #[test]
+    fn keeps_mode_when_owner_cannot_be_changed() {
+        let remote_fs = memory_fs(false, &[
+            ("/ems/run.sh", file(10, 0o100750, 0)),
+            ("/ems/run.sh.part", file(20, 0o100644, 1000)),
+        ]);
+        replace_remote_file(&remote_fs, Path::new("/ems/run.sh.part"), Path::new("/ems/run.sh")).unwrap();
+
+        assert_eq!(stat_of(&remote_fs, "/ems/run.sh"), Some(file(20, 0o750, 1000)));
+    }
+
+    #[test]
+    fn renames_new_file_without_touching_attributes() {
+        let remote_fs = memory_fs(false, &[("/ems/new.part", file(20, 0o100644, 1000))]);
+        replace_remote_file(&remote_fs, Path::new("/ems/new.part"), Path::new("/ems/new")).unwrap();
+        assert_eq!(stat_of(&remote_fs, "/ems/new"), Some(file(20, 0o100644, 1000)));
+
+        // 临时文件不存在时不会删除目标
+        let remote_fs = memory_fs(false, &[("/ems/app", file(10, 0o100755, 1000))]);
+        assert!(replace_remote_file(&remote_fs, Path::new("/ems/app.part"), Path::new("/ems/app")).is_err());
+        assert!(stat_of(&remote_fs, "/ems/app").is_some());
+    }
+}
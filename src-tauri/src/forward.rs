@@ -0,0 +1,836 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use ssh2::{Channel, Listener};
+use tauri::State;
+use serde::{Deserialize, Serialize};
+
+use crate::AppState;
+use crate::error::{EmsError, EmsResult};
+use crate::jump::write_pending;
+use crate::ssh::{SharedSshSession, SshSessions, run_blocking, with_nonblocking};
+use crate::logger::add_log_internal;
+use crate::socks::{self, SocksReply};
+
+// 等待新连接和转发数据时的轮询间隔
+const FORWARD_POLL_INTERVAL: Duration = Duration::from_millis(10);
+// 单次转发的缓冲区大小
+const FORWARD_BUFFER_SIZE: usize = 32 * 1024;
+// SOCKS5 握手的超时时间，防止异常客户端占用线程
+const SOCKS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// 转发连接的远端，即SSH通道；测试中用直连的TCP连接代替
+trait Tunnel: Read + Write {
+    fn send_eof(&mut self) -> io::Result<()>;
+    fn eof(&self) -> bool;
+    fn close(&mut self) -> io::Result<()>;
+}
+
+impl Tunnel for Channel {
+    fn send_eof(&mut self) -> io::Result<()> {
+        Channel::send_eof(self).map_err(io::Error::from)
+    }
+
+    fn eof(&self) -> bool {
+        Channel::eof(self)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        Channel::close(self).map_err(io::Error::from)
+    }
+}
+
+// 打开到目标地址的通道
+type OpenTunnel<T> = fn(&PortForward, &str, u16) -> EmsResult<T>;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ForwardKind {
+    // 本地监听，经SSH转发到远端可访问的地址
+    Local,
+    // 远端监听，经SSH转发到本地可访问的地址
+    Remote,
+    // 本地SOCKS5代理，目标地址由客户端指定（类似 ssh -D）
+    Dynamic,
+}
+
+// 创建端口转发的参数
+#[derive(Debug, Deserialize)]
+pub struct ForwardConfig {
+    // 监听地址，本地转发默认127.0.0.1，远程转发默认由服务器决定
+    bind_host: Option<String>,
+    // 监听端口，为0时自动分配
+    bind_port: u16,
+    target_host: String,
+    target_port: u16,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ForwardInfo {
+    forward_id: String,
+    session_id: String,
+    kind: ForwardKind,
+    bind_host: String,
+    bind_port: u16,
+    target_host: String,
+    target_port: u16,
+    // active / closed / error
+    status: String,
+    error: Option<String>,
+    active_connections: usize,
+    // 本地 -> 远端
+    bytes_sent: u64,
+    // 远端 -> 本地
+    bytes_received: u64,
+}
+
+// 一条端口转发规则
+pub struct PortForward {
+    info: Mutex<ForwardInfo>,
+    ssh_session: SharedSshSession,
+    stopped: AtomicBool,
+    active_connections: AtomicUsize,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl PortForward {
+    fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        self.set_status("closed", None);
+    }
+
+    fn set_status(&self, status: &str, error: Option<String>) {
+        if let Ok(mut info) = self.info.lock() {
+            // 已关闭的转发不再改变状态
+            if info.status != "closed" {
+                info.status = status.to_string();
+                info.error = error;
+            }
+        }
+    }
+
+    fn session_id(&self) -> String {
+        self.info.lock().map(|info| info.session_id.clone()).unwrap_or_default()
+    }
+
+    // 当前状态与计数的快照
+    fn snapshot(&self) -> EmsResult<ForwardInfo> {
+        let mut info = self.info.lock()?.clone();
+        info.active_connections = self.active_connections.load(Ordering::Relaxed);
+        info.bytes_sent = self.bytes_sent.load(Ordering::Relaxed);
+        info.bytes_received = self.bytes_received.load(Ordering::Relaxed);
+        Ok(info)
+    }
+}
+
+// 所有端口转发，按转发ID索引
+pub struct ForwardSessions {
+    forwards: Mutex<HashMap<String, Arc<PortForward>>>,
+}
+
+impl ForwardSessions {
+    pub fn new() -> Self {
+        ForwardSessions {
+            forwards: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn insert(&self, forward_id: String, forward: Arc<PortForward>) -> EmsResult<()> {
+        let mut forwards = self.forwards.lock()?;
+        forwards.insert(forward_id, forward);
+        Ok(())
+    }
+
+    fn remove(&self, forward_id: &str) -> EmsResult<Option<Arc<PortForward>>> {
+        let mut forwards = self.forwards.lock()?;
+        Ok(forwards.remove(forward_id))
+    }
+
+    fn snapshot(&self) -> EmsResult<Vec<Arc<PortForward>>> {
+        let forwards = self.forwards.lock()?;
+        Ok(forwards.values().cloned().collect())
+    }
+
+    // 关闭某个会话上的所有转发（断开连接时调用）
+    pub fn close_session_forwards(&self, session_id: &str) -> EmsResult<usize> {
+        let mut forwards = self.forwards.lock()?;
+        let forward_ids: Vec<String> = forwards
+            .iter()
+            .filter(|(_, forward)| forward.session_id() == session_id)
+            .map(|(forward_id, _)| forward_id.clone())
+            .collect();
+
+        for forward_id in &forward_ids {
+            if let Some(forward) = forwards.remove(forward_id) {
+                forward.stop();
+            }
+        }
+        Ok(forward_ids.len())
+    }
+}
+
+// 初始化端口转发状态
+pub fn init_forward_state() -> ForwardSessions {
+    ForwardSessions::new()
+}
+
+fn new_forward(
+    session_id: String,
+    ssh_session: SharedSshSession,
+    kind: ForwardKind,
+    bind: (String, u16),
+    target: (String, u16),
+) -> Arc<PortForward> {
+    Arc::new(PortForward {
+        info: Mutex::new(ForwardInfo {
+            forward_id: uuid::Uuid::new_v4().to_string(),
+            session_id,
+            kind,
+            bind_host: bind.0,
+            bind_port: bind.1,
+            target_host: target.0,
+            target_port: target.1,
+            status: "active".to_string(),
+            error: None,
+            active_connections: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+        }),
+        ssh_session,
+        stopped: AtomicBool::new(false),
+        active_connections: AtomicUsize::new(0),
+        bytes_sent: AtomicU64::new(0),
+        bytes_received: AtomicU64::new(0),
+    })
+}
+
+// 创建本地转发：在本机监听，连接经SSH转发到远端的 target_host:target_port
+#[tauri::command]
+pub async fn start_local_forward(
+    config: ForwardConfig,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    forward_state: State<'_, ForwardSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<ForwardInfo> {
+    let ssh_session = ssh_state.get(&session_id)?;
+    let bind_host = config.bind_host.unwrap_or_else(|| "127.0.0.1".to_string());
+
+    let listener = TcpListener::bind((bind_host.as_str(), config.bind_port))
+        .map_err(|e| EmsError::Io(format!("无法监听 {}:{}: {}", bind_host, config.bind_port, e)))?;
+    listener.set_nonblocking(true)?;
+    let bind_port = listener.local_addr()?.port();
+
+    let forward = new_forward(
+        session_id,
+        ssh_session,
+        ForwardKind::Local,
+        (bind_host, bind_port),
+        (config.target_host, config.target_port),
+    );
+    let info = forward.snapshot()?;
+    forward_state.insert(info.forward_id.clone(), forward.clone())?;
+
+    thread::spawn(move || accept_local(forward, listener, open_direct_tcpip));
+
+    let log_message = format!(
+        "创建本地端口转发: {}:{} -> {}:{} (转发ID: {})",
+        info.bind_host, info.bind_port, info.target_host, info.target_port, info.forward_id
+    );
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("✅ [FORWARD] {}", log_message);
+
+    Ok(info)
+}
+
+// 创建远程转发：在远端监听 bind_host:bind_port，连接经SSH转发到本机可访问的 target_host:target_port
+#[tauri::command]
+pub async fn start_remote_forward(
+    config: ForwardConfig,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    forward_state: State<'_, ForwardSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<ForwardInfo> {
+    let ssh_session = ssh_state.get(&session_id)?;
+
+    let listen_session = ssh_session.clone();
+    let listen_host = config.bind_host.clone();
+    let bind_port = config.bind_port;
+    let (listener, bound_port) = run_blocking(move || {
+        let ssh_guard = listen_session.lock()?;
+        let session = ssh_guard.session.as_ref().ok_or(EmsError::NotConnected)?;
+        session.channel_forward_listen(bind_port, listen_host.as_deref(), None)
+            .map_err(|e| EmsError::Ssh(format!("远端无法监听端口 {}: {}", bind_port, e)))
+    }).await?;
+
+    let forward = new_forward(
+        session_id,
+        ssh_session,
+        ForwardKind::Remote,
+        (config.bind_host.unwrap_or_else(|| "localhost".to_string()), bound_port),
+        (config.target_host, config.target_port),
+    );
+    let info = forward.snapshot()?;
+    forward_state.insert(info.forward_id.clone(), forward.clone())?;
+
+    thread::spawn(move || accept_remote(forward, listener));
+
+    let log_message = format!(
+        "创建远程端口转发: {}:{} -> {}:{} (转发ID: {})",
+        info.bind_host, info.bind_port, info.target_host, info.target_port, info.forward_id
+    );
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("✅ [FORWARD] {}", log_message);
+
+    Ok(info)
+}
+
+// 创建动态转发：在本机启动SOCKS5代理，每个客户端连接经SSH转发到其请求的地址
+// bind_port 为0时自动分配端口
+#[tauri::command]
+pub async fn start_dynamic_forward(
+    session_id: String,
+    bind_host: Option<String>,
+    bind_port: u16,
+    ssh_state: State<'_, SshSessions>,
+    forward_state: State<'_, ForwardSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<ForwardInfo> {
+    let ssh_session = ssh_state.get(&session_id)?;
+    let bind_host = bind_host.unwrap_or_else(|| "127.0.0.1".to_string());
+
+    let listener = TcpListener::bind((bind_host.as_str(), bind_port))
+        .map_err(|e| EmsError::Io(format!("无法监听 {}:{}: {}", bind_host, bind_port, e)))?;
+    listener.set_nonblocking(true)?;
+    let bind_port = listener.local_addr()?.port();
+
+    let forward = new_forward(
+        session_id,
+        ssh_session,
+        ForwardKind::Dynamic,
+        (bind_host, bind_port),
+        ("*".to_string(), 0),
+    );
+    let info = forward.snapshot()?;
+    forward_state.insert(info.forward_id.clone(), forward.clone())?;
+
+    thread::spawn(move || accept_dynamic(forward, listener, open_direct_tcpip));
+
+    let log_message = format!(
+        "创建SOCKS5动态转发: {}:{} (转发ID: {})",
+        info.bind_host, info.bind_port, info.forward_id
+    );
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("✅ [FORWARD] {}", log_message);
+
+    Ok(info)
+}
+
+// 列出端口转发，可按会话过滤
+#[tauri::command]
+pub async fn list_forwards(
+    session_id: Option<String>,
+    forward_state: State<'_, ForwardSessions>,
+) -> EmsResult<Vec<ForwardInfo>> {
+    let mut result = Vec::new();
+    for forward in forward_state.snapshot()? {
+        let info = forward.snapshot()?;
+        if session_id.as_ref().is_none_or(|session_id| *session_id == info.session_id) {
+            result.push(info);
+        }
+    }
+    Ok(result)
+}
+
+// 关闭端口转发
+#[tauri::command]
+pub async fn stop_forward(
+    forward_id: String,
+    forward_state: State<'_, ForwardSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    let forward = forward_state.remove(&forward_id)?
+        .ok_or_else(|| EmsError::NotFound(format!("端口转发不存在: {}", forward_id)))?;
+    forward.stop();
+
+    add_log_internal(&app_state, "INFO", &format!("关闭端口转发: {}", forward_id));
+    println!("✅ [FORWARD] 关闭端口转发: {}", forward_id);
+
+    Ok(())
+}
+
+// 在SSH会话上打开 direct-tcpip 通道
+fn open_direct_tcpip(forward: &PortForward, host: &str, port: u16) -> EmsResult<Channel> {
+    let ssh_guard = forward.ssh_session.lock()?;
+    let session = ssh_guard.session.as_ref().ok_or(EmsError::NotConnected)?;
+    session.channel_direct_tcpip(host, port, None)
+        .map_err(|e| EmsError::Ssh(e.to_string()))
+}
+
+// 本地转发的监听线程：每个新连接打开一个通道
+fn accept_local<T: Tunnel + Send + 'static>(forward: Arc<PortForward>, listener: TcpListener, open: OpenTunnel<T>) {
+    let (target_host, target_port) = match forward.info.lock() {
+        Ok(info) => (info.target_host.clone(), info.target_port),
+        Err(_) => return,
+    };
+
+    while !forward.is_stopped() {
+        let local = match listener.accept() {
+            Ok((local, _)) => local,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(FORWARD_POLL_INTERVAL);
+                continue;
+            }
+            Err(e) => {
+                forward.set_status("error", Some(format!("接受本地连接失败: {}", e)));
+                break;
+            }
+        };
+
+        match open(&forward, &target_host, target_port) {
+            Ok(channel) => {
+                let connection_forward = forward.clone();
+                thread::spawn(move || pump_connection(connection_forward, channel, local));
+            }
+            Err(EmsError::LockPoisoned(_)) => break,
+            Err(e @ EmsError::NotConnected) => {
+                forward.set_status("error", Some(e.to_string()));
+                break;
+            }
+            Err(e) => println!("⚠️ [FORWARD] 无法转发到 {}:{}: {}", target_host, target_port, e),
+        }
+    }
+}
+
+// 远程转发的监听线程：每个远端连接转发到本地目标地址
+fn accept_remote(forward: Arc<PortForward>, mut listener: Listener) {
+    let (target_host, target_port) = match forward.info.lock() {
+        Ok(info) => (info.target_host.clone(), info.target_port),
+        Err(_) => return,
+    };
+
+    while !forward.is_stopped() {
+        let accepted = with_nonblocking(&forward.ssh_session, || {
+            listener.accept().map_err(io::Error::from)
+        });
+        let channel = match accepted {
+            Ok(Ok(channel)) => channel,
+            Ok(Err(ref e)) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(FORWARD_POLL_INTERVAL);
+                continue;
+            }
+            Ok(Err(e)) => {
+                forward.set_status("error", Some(format!("接受远端连接失败: {}", e)));
+                break;
+            }
+            Err(e) => {
+                forward.set_status("error", Some(e.to_string()));
+                break;
+            }
+        };
+
+        match TcpStream::connect((target_host.as_str(), target_port)) {
+            Ok(local) => {
+                let connection_forward = forward.clone();
+                thread::spawn(move || pump_connection(connection_forward, channel, local));
+            }
+            Err(e) => println!("⚠️ [FORWARD] 无法连接本地目标 {}:{}: {}", target_host, target_port, e),
+        }
+    }
+
+    // 取消远端监听需要访问会话
+    if let Ok(_ssh_guard) = forward.ssh_session.lock() {
+        drop(listener);
+    }
+}
+
+// 动态转发的监听线程：每个客户端连接在独立线程中完成SOCKS5握手
+fn accept_dynamic<T: Tunnel + Send + 'static>(forward: Arc<PortForward>, listener: TcpListener, open: OpenTunnel<T>) {
+    while !forward.is_stopped() {
+        match listener.accept() {
+            Ok((client, _)) => {
+                let connection_forward = forward.clone();
+                thread::spawn(move || serve_socks_client(connection_forward, client, open));
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(FORWARD_POLL_INTERVAL);
+            }
+            Err(e) => {
+                forward.set_status("error", Some(format!("接受本地连接失败: {}", e)));
+                break;
+            }
+        }
+    }
+}
+
+// 处理一个SOCKS5客户端：读取目标地址，打开通道后开始转发
+fn serve_socks_client<T: Tunnel>(forward: Arc<PortForward>, mut client: TcpStream, open: OpenTunnel<T>) {
+    // 监听套接字是非阻塞的，握手阶段改回阻塞并设置超时
+    let prepared = client.set_nonblocking(false)
+        .and_then(|_| client.set_read_timeout(Some(SOCKS_HANDSHAKE_TIMEOUT)));
+    if let Err(e) = prepared {
+        println!("⚠️ [FORWARD] 无法初始化SOCKS5连接: {}", e);
+        return;
+    }
+
+    let (target_host, target_port) = match socks::handshake(&mut client) {
+        Ok(target) => target,
+        Err(e) => {
+            println!("⚠️ [FORWARD] SOCKS5握手失败: {}", e);
+            return;
+        }
+    };
+
+    match open(&forward, &target_host, target_port) {
+        Ok(mut channel) => {
+            if socks::send_reply(&mut client, SocksReply::Succeeded).is_ok() {
+                pump_connection(forward, channel, client);
+            } else if let Ok(_ssh_guard) = forward.ssh_session.lock() {
+                let _ = channel.close();
+            }
+        }
+        Err(EmsError::LockPoisoned(_)) => {
+            let _ = socks::send_reply(&mut client, SocksReply::GeneralFailure);
+        }
+        Err(e) => {
+            println!("⚠️ [FORWARD] 无法转发到 {}:{}: {}", target_host, target_port, e);
+            let _ = socks::send_reply(&mut client, SocksReply::HostUnreachable);
+        }
+    }
+}
+
+// 在本地连接和SSH通道之间双向转发数据，直到通道EOF或转发被停止
+fn pump_connection<T: Tunnel>(forward: Arc<PortForward>, mut channel: T, mut local: TcpStream) {
+    forward.active_connections.fetch_add(1, Ordering::Relaxed);
+    if let Err(e) = forward_data(&forward, &mut channel, &mut local) {
+        println!("⚠️ [FORWARD] 转发连接异常结束: {}", e);
+    }
+    forward.active_connections.fetch_sub(1, Ordering::Relaxed);
+
+    if let Ok(_ssh_guard) = forward.ssh_session.lock() {
+        let _ = channel.close();
+    }
+}
+
+fn forward_data<T: Tunnel>(forward: &PortForward, channel: &mut T, local: &mut TcpStream) -> io::Result<()> {
+    local.set_nonblocking(true)?;
+
+    let mut local_buffer = vec![0u8; FORWARD_BUFFER_SIZE];
+    let mut channel_buffer = vec![0u8; FORWARD_BUFFER_SIZE];
+    let mut to_channel = Vec::new();
+    let mut to_local = Vec::new();
+    let mut local_closed = false;
+    let mut eof_sent = false;
+
+    while !forward.is_stopped() {
+        let mut progressed = false;
+
+        // 本地 -> 远端
+        if to_channel.is_empty() && !local_closed {
+            match local.read(&mut local_buffer) {
+                Ok(0) => {
+                    local_closed = true;
+                    progressed = true;
+                }
+                Ok(n) => {
+                    to_channel.extend_from_slice(&local_buffer[..n]);
+                    forward.bytes_sent.fetch_add(n as u64, Ordering::Relaxed);
+                    progressed = true;
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // 通道上的读写需要持有会话锁，每次轮询后释放
+        let (sent, received, eof) = with_nonblocking(&forward.ssh_session, || {
+            let sent = write_pending(channel, &mut to_channel)?;
+            if local_closed && to_channel.is_empty() && !eof_sent {
+                let _ = channel.send_eof();
+                eof_sent = true;
+            }
+
+            let mut received = 0;
+            if to_local.is_empty() {
+                match channel.read(&mut channel_buffer) {
+                    Ok(n) => received = n,
+                    Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            Ok((sent, received, channel.eof()))
+        }).map_err(|e| io::Error::other(e.to_string()))??;
+
+        if received > 0 {
+            to_local.extend_from_slice(&channel_buffer[..received]);
+            forward.bytes_received.fetch_add(received as u64, Ordering::Relaxed);
+        }
+        progressed |= sent || received > 0;
+        progressed |= write_pending(local, &mut to_local)?;
+
+        // 本地只关闭了写入（半关闭）时远端可能还在回应，读到通道EOF后才结束
+        if to_local.is_empty() && eof {
+            break;
+        }
+        if !progressed {
+            thread::sleep(FORWARD_POLL_INTERVAL);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Shutdown;
+    use crate::ssh::SshSession;
+
+    // 代替SSH通道：直接连接目标地址，相当于服务器端处理 direct-tcpip
+    struct TcpTunnel {
+        stream: TcpStream,
+        eof: bool,
+    }
+
+    impl Read for TcpTunnel {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.stream.read(buf)?;
+            if n == 0 && !buf.is_empty() {
+                self.eof = true;
+            }
+            Ok(n)
+        }
+    }
+
+    impl Write for TcpTunnel {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.stream.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.stream.flush()
+        }
+    }
+
+    impl Tunnel for TcpTunnel {
+        fn send_eof(&mut self) -> io::Result<()> {
+            self.stream.shutdown(Shutdown::Write)
+        }
+
+        fn eof(&self) -> bool {
+            self.eof
+        }
+
+        fn close(&mut self) -> io::Result<()> {
+            self.stream.shutdown(Shutdown::Both)
+        }
+    }
+
+    fn open_tcp_tunnel(_forward: &PortForward, host: &str, port: u16) -> EmsResult<TcpTunnel> {
+        let stream = TcpStream::connect((host, port)).map_err(|e| EmsError::Ssh(e.to_string()))?;
+        stream.set_nonblocking(true)?;
+        Ok(TcpTunnel { stream, eof: false })
+    }
+
+    // 转发需要一个SSH会话来加锁和切换非阻塞模式，未连接的会话即可
+    fn test_forward(kind: ForwardKind, bind_port: u16, target: (&str, u16)) -> Arc<PortForward> {
+        let mut ssh_session = SshSession::new();
+        ssh_session.session = Some(ssh2::Session::new().unwrap());
+        new_forward(
+            "test-session".to_string(),
+            Arc::new(Mutex::new(ssh_session)),
+            kind,
+            ("127.0.0.1".to_string(), bind_port),
+            (target.0.to_string(), target.1),
+        )
+    }
+
+    fn listen() -> (TcpListener, u16) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (listener, port)
+    }
+
+    // 只处理一个请求的HTTP服务，读到请求头结束后回应并关闭连接
+    fn serve_http_once(response: &'static [u8]) -> u16 {
+        let (server, port) = listen();
+        thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = conn.read(&mut buffer).unwrap();
+                assert!(n > 0, "请求不完整");
+                request.extend_from_slice(&buffer[..n]);
+            }
+            assert!(request.starts_with(b"GET /status HTTP/1.0"));
+            conn.write_all(response).unwrap();
+        });
+        port
+    }
+
+    fn wait_for_connections(forward: &PortForward, count: usize) {
+        for _ in 0..500 {
+            if forward.active_connections.load(Ordering::Relaxed) == count {
+                return;
+            }
+            thread::sleep(FORWARD_POLL_INTERVAL);
+        }
+        panic!("活动连接数没有变为 {}", count);
+    }
+
+    const REQUEST: &[u8] = b"GET /status HTTP/1.0\r\n\r\n";
+    const RESPONSE: &[u8] = b"HTTP/1.0 200 OK\r\nContent-Length: 2\r\n\r\nok";
+
+    fn socks_connect(proxy_port: u16, host: &str, port: u16) -> (TcpStream, u8) {
+        let mut client = TcpStream::connect(("127.0.0.1", proxy_port)).unwrap();
+        client.write_all(&[5, 1, 0]).unwrap();
+        let mut method = [0u8; 2];
+        client.read_exact(&mut method).unwrap();
+        assert_eq!(method, [5, 0]);
+
+        let mut request = vec![5, 1, 0, 3, host.len() as u8];
+        request.extend_from_slice(host.as_bytes());
+        request.extend_from_slice(&port.to_be_bytes());
+        client.write_all(&request).unwrap();
+        let mut reply = [0u8; 10];
+        client.read_exact(&mut reply).unwrap();
+        (client, reply[1])
+    }
+
+    #[test]
+    fn dynamic_forward_proxies_http_through_stand_in_channel() {
+        let http_port = serve_http_once(RESPONSE);
+        let (listener, proxy_port) = listen();
+        listener.set_nonblocking(true).unwrap();
+        let forward = test_forward(ForwardKind::Dynamic, proxy_port, ("*", 0));
+        let accept_forward = forward.clone();
+        let acceptor = thread::spawn(move || accept_dynamic(accept_forward, listener, open_tcp_tunnel));
+
+        let (mut client, reply) = socks_connect(proxy_port, "localhost", http_port);
+        assert_eq!(reply, SocksReply::Succeeded as u8);
+        client.write_all(REQUEST).unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        assert_eq!(response, RESPONSE);
+
+        // 服务器关闭后转发结束，计数在连接关闭前已更新
+        let info = forward.snapshot().unwrap();
+        assert_eq!((info.bytes_sent, info.bytes_received), (REQUEST.len() as u64, RESPONSE.len() as u64));
+        assert_eq!(info.active_connections, 0);
+        assert_eq!(info.status, "active");
+
+        // 目标无法连接时回应 HostUnreachable，转发本身不受影响
+        let (_, closed_port) = listen();
+        let (_, reply) = socks_connect(proxy_port, "127.0.0.1", closed_port);
+        assert_eq!(reply, SocksReply::HostUnreachable as u8);
+        assert_eq!(forward.snapshot().unwrap().status, "active");
+
+        forward.stop();
+        acceptor.join().unwrap();
+        assert_eq!(forward.snapshot().unwrap().status, "closed");
+        assert!(TcpStream::connect(("127.0.0.1", proxy_port)).is_err());
+    }
+
+    #[test]
+    fn local_forward_delivers_response_after_client_half_close() {
+        // 服务器读到EOF后才回应，客户端发送请求后立即关闭写入
+        let (server, server_port) = listen();
+        let server_thread = thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let mut request = Vec::new();
+            conn.read_to_end(&mut request).unwrap();
+            assert_eq!(request, REQUEST);
+            thread::sleep(Duration::from_millis(50));
+            conn.write_all(RESPONSE).unwrap();
+        });
+
+        let (listener, local_port) = listen();
+        listener.set_nonblocking(true).unwrap();
+        let forward = test_forward(ForwardKind::Local, local_port, ("127.0.0.1", server_port));
+        let accept_forward = forward.clone();
+        let acceptor = thread::spawn(move || accept_local(accept_forward, listener, open_tcp_tunnel));
+
+        let mut client = TcpStream::connect(("127.0.0.1", local_port)).unwrap();
+        client.write_all(REQUEST).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut response = Vec::new();
+        client.read_to_end(&mut response).unwrap();
+        assert_eq!(response, RESPONSE);
+        server_thread.join().unwrap();
+
+        let info = forward.snapshot().unwrap();
+        assert_eq!((info.bytes_sent, info.bytes_received), (REQUEST.len() as u64, RESPONSE.len() as u64));
+        assert_eq!(info.active_connections, 0);
+
+        forward.stop();
+        acceptor.join().unwrap();
+        assert_eq!(forward.snapshot().unwrap().status, "closed");
+    }
+
+    #[test]
+    fn remote_forward_counts_bytes_in_both_directions() {
+        // 远程转发中通道一端是远端的客户端，本地连接到目标服务
+        let http_port = serve_http_once(RESPONSE);
+        let (remote, remote_port) = listen();
+        let forward = test_forward(ForwardKind::Remote, 0, ("127.0.0.1", http_port));
+        let channel = open_tcp_tunnel(&forward, "127.0.0.1", remote_port).unwrap();
+        let (mut remote_client, _) = remote.accept().unwrap();
+        let local = TcpStream::connect(("127.0.0.1", http_port)).unwrap();
+        let pump_forward = forward.clone();
+        let pump = thread::spawn(move || pump_connection(pump_forward, channel, local));
+
+        remote_client.write_all(REQUEST).unwrap();
+        let mut response = Vec::new();
+        remote_client.read_to_end(&mut response).unwrap();
+        assert_eq!(response, RESPONSE);
+        drop(remote_client);
+        pump.join().unwrap();
+
+        // 本地 -> 远端计入发送，远端 -> 本地计入接收
+        let info = forward.snapshot().unwrap();
+        assert_eq!((info.bytes_sent, info.bytes_received), (RESPONSE.len() as u64, REQUEST.len() as u64));
+        assert_eq!(info.active_connections, 0);
+    }
+
+    #[test]
+    fn stopping_forward_closes_idle_connections() {
+        // 目标服务保持连接直到读到EOF
+        let (server, server_port) = listen();
+        let server_thread = thread::spawn(move || {
+            let (mut conn, _) = server.accept().unwrap();
+            let mut request = Vec::new();
+            conn.read_to_end(&mut request).unwrap();
+            request
+        });
+
+        let (listener, local_port) = listen();
+        listener.set_nonblocking(true).unwrap();
+        let forward = test_forward(ForwardKind::Local, local_port, ("127.0.0.1", server_port));
+        let accept_forward = forward.clone();
+        let acceptor = thread::spawn(move || accept_local(accept_forward, listener, open_tcp_tunnel));
+
+        let mut client = TcpStream::connect(("127.0.0.1", local_port)).unwrap();
+        wait_for_connections(&forward, 1);
+
+        forward.stop();
+        acceptor.join().unwrap();
+        // 本地连接和通道都被关闭
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).unwrap();
+        assert!(received.is_empty());
+        assert!(server_thread.join().unwrap().is_empty());
+        wait_for_connections(&forward, 0);
+        assert_eq!(forward.snapshot().unwrap().status, "closed");
+    }
+}
@@ -0,0 +1,155 @@
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+use ssh2::{Channel, Session};
+use tauri::AppHandle;
+
+use crate::SshConnectionConfig;
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{establish_session, open_tcp};
+
+// 隧道空闲时的轮询间隔
+const TUNNEL_POLL_INTERVAL: Duration = Duration::from_millis(5);
+// 单次转发的缓冲区大小
+const TUNNEL_BUFFER_SIZE: usize = 32 * 1024;
+
+// 打开到目标主机的TCP连接，配置了跳板机时依次经由各跳板机转发
+// 返回的连接可以直接交给 Session::set_tcp_stream 使用
+pub fn open_stream(
+    jump_hosts: &[SshConnectionConfig],
+    host: &str,
+    port: u16,
+    app_handle: &AppHandle,
+) -> EmsResult<TcpStream> {
+    let Some(first_hop) = jump_hosts.first() else {
+        return open_tcp(host, port);
+    };
+
+    let mut tcp = open_tcp(&first_hop.host, first_hop.port)?;
+    for (index, hop) in jump_hosts.iter().enumerate() {
+        // 当前跳板机需要转发到的下一站：下一个跳板机或最终目标
+        let (next_host, next_port) = match jump_hosts.get(index + 1) {
+            Some(next_hop) => (next_hop.host.as_str(), next_hop.port),
+            None => (host, port),
+        };
+
+        let (session, _) = establish_session(tcp, hop, app_handle).map_err(|e| match e {
+            // 主机密钥相关的错误需要保留原样，前端据此提示用户确认
+            // 认证输入超时或取消也保留原样
+            EmsError::UnknownHost { .. }
+            | EmsError::HostKeyMismatch { .. }
+            | EmsError::Timeout(_)
+            | EmsError::Cancelled(_) => e,
+            e => EmsError::Ssh(format!("跳板机 {}:{} 连接失败: {}", hop.host, hop.port, e)),
+        })?;
+        println!("🔧 [SSH] 经由跳板机 {}:{} 转发到 {}:{}", hop.host, hop.port, next_host, next_port);
+
+        tcp = forward_through(session, next_host, next_port)?;
+    }
+
+    Ok(tcp)
+}
+
+// 在跳板机上打开direct-tcpip通道，并桥接到一个本地回环连接上
+fn forward_through(session: Session, host: &str, port: u16) -> EmsResult<TcpStream> {
+    let channel = session.channel_direct_tcpip(host, port, None)
+        .map_err(|e| EmsError::Ssh(format!("跳板机无法转发到 {}:{}: {}", host, port, e)))?;
+
+    let listener = TcpListener::bind(("127.0.0.1", 0))?;
+    let client = TcpStream::connect(listener.local_addr()?)?;
+    let (bridge, peer_addr) = listener.accept()?;
+
+    // 确认接入的是我们自己的连接，而不是本机其他进程
+    if peer_addr != client.local_addr()? {
+        return Err(EmsError::Ssh("跳板机隧道被其他本地连接抢占".to_string()));
+    }
+
+    thread::spawn(move || {
+        if let Err(e) = pump_tunnel(&session, channel, bridge) {
+            println!("⚠️ [SSH] 跳板机隧道异常结束: {}", e);
+        }
+        let _ = session.disconnect(None, "Tunnel closed", None);
+    });
+
+    Ok(client)
+}
+
+// 在本地连接和跳板机通道之间双向转发数据，直到任意一端关闭
+fn pump_tunnel(session: &Session, mut channel: Channel, mut local: TcpStream) -> std::io::Result<()> {
+    // 跳板机会话只被本线程使用，整个生命周期保持非阻塞
+    session.set_blocking(false);
+    local.set_nonblocking(true)?;
+
+    let mut buffer = vec![0u8; TUNNEL_BUFFER_SIZE];
+    let mut to_channel = Vec::new();
+    let mut to_local = Vec::new();
+    let mut local_closed = false;
+
+    loop {
+        let mut progressed = false;
+
+        // 本地 -> 跳板机
+        if to_channel.is_empty() && !local_closed {
+            match local.read(&mut buffer) {
+                Ok(0) => {
+                    local_closed = true;
+                    progressed = true;
+                    let _ = channel.send_eof();
+                }
+                Ok(n) => {
+                    to_channel.extend_from_slice(&buffer[..n]);
+                    progressed = true;
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+        progressed |= write_pending(&mut channel, &mut to_channel)?;
+
+        // 跳板机 -> 本地
+        if to_local.is_empty() {
+            match channel.read(&mut buffer) {
+                Ok(0) => {}
+                Ok(n) => {
+                    to_local.extend_from_slice(&buffer[..n]);
+                    progressed = true;
+                }
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+        progressed |= write_pending(&mut local, &mut to_local)?;
+
+        // 本地关闭后继续读取跳板机上尚未送达的数据，直到通道EOF
+        if to_local.is_empty() && channel.eof() {
+            break;
+        }
+
+        if !progressed {
+            // 跳板机会话上没有其他通道，需要自己维持keepalive
+            let _ = session.keepalive_send();
+            thread::sleep(TUNNEL_POLL_INTERVAL);
+        }
+    }
+
+    session.set_blocking(true);
+    let _ = channel.close();
+    Ok(())
+}
+
+// 尽量写出待发送的数据，返回是否有数据被写出
+pub fn write_pending<W: Write>(writer: &mut W, pending: &mut Vec<u8>) -> std::io::Result<bool> {
+    let mut written = 0;
+    while written < pending.len() {
+        match writer.write(&pending[written..]) {
+            Ok(0) => break,
+            Ok(n) => written += n,
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    pending.drain(..written);
+    Ok(written > 0)
+}
@@ -0,0 +1,181 @@
+use std::path::PathBuf;
+use ssh2::{CheckResult, HashType, KnownHostFileKind, KnownHostKeyFormat, KnownHosts, Session};
+use tauri::{AppHandle, State};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+
+use crate::{AppState, SshConnectionConfig, app_config_dir};
+use crate::error::{EmsError, EmsResult};
+use crate::jump::open_stream;
+use crate::ssh::{resolve_config, run_blocking};
+use crate::vault::Vault;
+use crate::logger::add_log_internal;
+use crate::profiles::ProfileStore;
+
+// known_hosts文件路径
+fn known_hosts_path() -> EmsResult<PathBuf> {
+    Ok(app_config_dir()?.join("known_hosts"))
+}
+
+// known_hosts中的主机名，非22端口使用 [host]:port 格式
+fn host_entry_name(host: &str, port: u16) -> String {
+    if port == 22 {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+// 计算主机密钥的SHA-256指纹（与OpenSSH格式一致）
+pub fn host_key_fingerprint(session: &Session) -> Option<String> {
+    session
+        .host_key_hash(HashType::Sha256)
+        .map(|hash| format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)))
+}
+
+// 读取应用自己的known_hosts文件
+fn load_known_hosts(session: &Session) -> EmsResult<(KnownHosts, PathBuf)> {
+    let path = known_hosts_path()?;
+    let mut known_hosts = session.known_hosts()?;
+
+    if path.exists() {
+        known_hosts.read_file(&path, KnownHostFileKind::OpenSSH)?;
+    }
+
+    Ok((known_hosts, path))
+}
+
+// 握手后校验服务器的主机密钥
+pub fn verify_host_key(session: &Session, host: &str, port: u16) -> EmsResult<()> {
+    let (key, _) = session.host_key()
+        .ok_or_else(|| EmsError::Ssh("无法获取服务器主机密钥".to_string()))?;
+    let fingerprint = host_key_fingerprint(session).unwrap_or_else(|| "未知".to_string());
+    let (known_hosts, _) = load_known_hosts(session)?;
+
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(EmsError::UnknownHost {
+            host: host.to_string(),
+            port,
+            fingerprint,
+        }),
+        CheckResult::Mismatch => Err(EmsError::HostKeyMismatch {
+            host: host.to_string(),
+            port,
+            fingerprint,
+        }),
+        CheckResult::Failure => Err(EmsError::Ssh(format!("无法校验 {}:{} 的主机密钥", host, port))),
+    }
+}
+
+// 用户确认后接受（或替换）主机密钥
+// 参数大多是Tauri注入的状态，不适合合并成结构体
+#[allow(clippy::too_many_arguments)]
+#[tauri::command]
+pub async fn accept_host_key(
+    host: String,
+    port: u16,
+    fingerprint: String,
+    // 经由跳板机连接时传入完整的连接配置，用于找到到达该主机的路径
+    config: Option<SshConnectionConfig>,
+    // 使用保存的连接配置时传入配置ID，代替 config
+    profile_id: Option<String>,
+    app_handle: AppHandle,
+    profile_store: State<'_, ProfileStore>,
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<String> {
+    let log_message = format!("接受主机密钥: {}:{} ({})", host, port, fingerprint);
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("🔧 [SSH] {}", log_message);
+
+    let config = match profile_id {
+        Some(profile_id) => Some(profile_store.get(&profile_id)?.config().clone()),
+        None => config,
+    };
+    // 与连接时一样用SSH配置文件补全，才能得到相同的跳板机路径
+    // 经过的跳板机需要登录，密码从保险库取出
+    let jump_hosts = match config {
+        Some(config) => {
+            let config = vault.resolve_config(&resolve_config(config, &app_state).await)?;
+            jump_route(&config, &host, port)
+        }
+        None => Vec::new(),
+    };
+    let store_host = host.clone();
+    let result = run_blocking(move || store_host_key(&jump_hosts, &store_host, port, &fingerprint, &app_handle)).await;
+
+    match result {
+        Ok(replaced) => {
+            let success_msg = if replaced {
+                format!("已替换 {}:{} 的主机密钥", host, port)
+            } else {
+                format!("已添加 {}:{} 的主机密钥", host, port)
+            };
+            add_log_internal(&app_state, "INFO", &success_msg);
+            println!("✅ [SSH] {}", success_msg);
+            Ok(success_msg)
+        }
+        Err(e) => {
+            let error_msg = format!("保存主机密钥失败: {}", e);
+            add_log_internal(&app_state, "ERROR", &error_msg);
+            println!("❌ [SSH] {}", error_msg);
+            Err(e)
+        }
+    }
+}
+
+// 到达指定主机需要经过的跳板机：该主机本身是跳板机时为它之前的跳板机，否则为全部跳板机
+fn jump_route(config: &SshConnectionConfig, host: &str, port: u16) -> Vec<SshConnectionConfig> {
+    let hops = config.jump_hosts
+        .iter()
+        .position(|hop| hop.host == host && hop.port == port)
+        .unwrap_or(config.jump_hosts.len());
+    config.jump_hosts[..hops].to_vec()
+}
+
+// 重新获取主机密钥，确认指纹与用户看到的一致后写入known_hosts
+// 返回是否替换了已有的记录
+fn store_host_key(
+    jump_hosts: &[SshConnectionConfig],
+    host: &str,
+    port: u16,
+    fingerprint: &str,
+    app_handle: &AppHandle,
+) -> EmsResult<bool> {
+    let tcp = open_stream(jump_hosts, host, port, app_handle)?;
+    let mut session = Session::new()?;
+    session.set_tcp_stream(tcp);
+    session.handshake()?;
+
+    let current_fingerprint = host_key_fingerprint(&session)
+        .ok_or_else(|| EmsError::Ssh("无法计算主机密钥指纹".to_string()))?;
+    if current_fingerprint != fingerprint {
+        return Err(EmsError::HostKeyMismatch {
+            host: host.to_string(),
+            port,
+            fingerprint: current_fingerprint,
+        });
+    }
+
+    let (key, key_type) = session.host_key()
+        .ok_or_else(|| EmsError::Ssh("无法获取服务器主机密钥".to_string()))?;
+    let (mut known_hosts, path) = load_known_hosts(&session)?;
+
+    // 删除该主机已有的记录
+    let entry_name = host_entry_name(host, port);
+    let mut replaced = false;
+    for existing in known_hosts.hosts()? {
+        if existing.name() == Some(entry_name.as_str()) {
+            known_hosts.remove(&existing)?;
+            replaced = true;
+        }
+    }
+
+    known_hosts.add(&entry_name, key, "added by ems_tools", KnownHostKeyFormat::from(key_type))?;
+    known_hosts.write_file(&path, KnownHostFileKind::OpenSSH)?;
+
+    let _ = session.disconnect(None, "Host key accepted", None);
+
+    Ok(replaced)
+}
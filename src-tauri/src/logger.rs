@@ -2,6 +2,7 @@ use tauri::State;
 use chrono::Local;
 
 use crate::{AppState, LogEntry};
+use crate::error::EmsResult;
 
 // 添加日志
 #[tauri::command]
@@ -9,7 +10,7 @@ pub async fn add_log(
     level: String,
     message: String,
     app_state: State<'_, AppState>,
-) -> Result<(), String> {
+) -> EmsResult<()> {
     add_log_internal(&app_state, &level, &message);
     Ok(())
 }
@@ -51,23 +52,17 @@ pub fn add_log_internal(
 #[tauri::command]
 pub async fn get_logs(
     app_state: State<'_, AppState>,
-) -> Result<Vec<LogEntry>, String> {
-    match app_state.log_entries.lock() {
-        Ok(logs) => Ok(logs.clone()),
-        Err(_) => Err("无法访问日志数据".to_string()),
-    }
+) -> EmsResult<Vec<LogEntry>> {
+    let logs = app_state.log_entries.lock()?;
+    Ok(logs.clone())
 }
 
 // 清除日志
 #[tauri::command]
 pub async fn clear_logs(
     app_state: State<'_, AppState>,
-) -> Result<(), String> {
-    match app_state.log_entries.lock() {
-        Ok(mut logs) => {
-            logs.clear();
-            Ok(())
-        },
-        Err(_) => Err("无法访问日志数据".to_string()),
-    }
+) -> EmsResult<()> {
+    let mut logs = app_state.log_entries.lock()?;
+    logs.clear();
+    Ok(())
 } 
\ No newline at end of file
@@ -4,17 +4,43 @@
 )]
 
 mod ssh;
+mod sftp;
 mod file_transfer;
+mod dir_transfer;
+mod transfer_journal;
 mod network;
 mod service;
 mod logger;
+mod known_hosts;
+mod shell;
+mod exec;
+mod error;
+mod monitor;
+mod jump;
+mod forward;
+mod socks;
+mod ssh_config;
+mod profiles;
+mod vault;
+mod auth_prompt;
+mod privilege;
+mod command;
+mod complete;
+mod recording;
+mod checksum;
 
-use std::sync::Mutex;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
 
-// 全局状态
+use error::{EmsError, EmsResult};
+
+// 全局状态（可克隆，以便移入后台线程）
+#[derive(Clone)]
 struct AppState {
-    log_entries: Mutex<Vec<LogEntry>>,
+    log_entries: Arc<Mutex<Vec<LogEntry>>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -24,18 +50,224 @@ struct LogEntry {
     message: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+// SSH认证方式
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SshAuthMethod {
+    // 密码认证
+    Password {
+        #[serde(default)]
+        password: String,
+        // 保存在凭据保险库中的密码
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        password_secret_id: Option<String>,
+    },
+    // 私钥文件认证
+    KeyFile {
+        private_key_path: String,
+        #[serde(default)]
+        public_key_path: Option<String>,
+        #[serde(default)]
+        passphrase: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        passphrase_secret_id: Option<String>,
+    },
+    // 内存中的PEM私钥认证
+    KeyMemory {
+        #[serde(default)]
+        private_key: String,
+        // 保存在凭据保险库中的私钥
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        private_key_secret_id: Option<String>,
+        #[serde(default)]
+        passphrase: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        passphrase_secret_id: Option<String>,
+    },
+    // ssh-agent认证
+    Agent,
+    // keyboard-interactive认证，例如密码加一次性验证码
+    KeyboardInteractive,
+}
+
+impl SshAuthMethod {
+    // 认证方式名称，用于日志和连接结果
+    fn name(&self) -> &'static str {
+        match self {
+            SshAuthMethod::Password { .. } => "password",
+            SshAuthMethod::KeyFile { .. } => "key_file",
+            SshAuthMethod::KeyMemory { .. } => "key_memory",
+            SshAuthMethod::Agent => "agent",
+            SshAuthMethod::KeyboardInteractive => "keyboard_interactive",
+        }
+    }
+}
+
+// 日志中不输出密码、口令和私钥
+impl fmt::Debug for SshAuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SshAuthMethod::Password { password, password_secret_id } => f
+                .debug_struct("Password")
+                .field("password", &redacted(Some(password)))
+                .field("password_secret_id", password_secret_id)
+                .finish(),
+            SshAuthMethod::KeyFile { private_key_path, public_key_path, passphrase, passphrase_secret_id } => f
+                .debug_struct("KeyFile")
+                .field("private_key_path", private_key_path)
+                .field("public_key_path", public_key_path)
+                .field("passphrase", &redacted(passphrase.as_ref()))
+                .field("passphrase_secret_id", passphrase_secret_id)
+                .finish(),
+            SshAuthMethod::KeyMemory { private_key, private_key_secret_id, passphrase, passphrase_secret_id } => f
+                .debug_struct("KeyMemory")
+                .field("private_key", &redacted(Some(private_key)))
+                .field("private_key_secret_id", private_key_secret_id)
+                .field("passphrase", &redacted(passphrase.as_ref()))
+                .field("passphrase_secret_id", passphrase_secret_id)
+                .finish(),
+            SshAuthMethod::Agent => f.write_str("Agent"),
+            SshAuthMethod::KeyboardInteractive => f.write_str("KeyboardInteractive"),
+        }
+    }
+}
+
+// 有内容的凭据显示为 <redacted>
+struct Redacted(&'static str);
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+fn redacted(secret: Option<&String>) -> Redacted {
+    match secret {
+        Some(secret) if !secret.is_empty() => Redacted("<redacted>"),
+        Some(_) => Redacted("\"\""),
+        None => Redacted("None"),
+    }
+}
+
+// 释放时清零密码、口令和私钥
+impl Drop for SshAuthMethod {
+    fn drop(&mut self) {
+        match self {
+            SshAuthMethod::Password { password, .. } => password.zeroize(),
+            SshAuthMethod::KeyFile { passphrase, .. } => passphrase.zeroize(),
+            SshAuthMethod::KeyMemory { private_key, passphrase, .. } => {
+                private_key.zeroize();
+                passphrase.zeroize();
+            }
+            SshAuthMethod::Agent | SshAuthMethod::KeyboardInteractive => {}
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
 struct SshConnectionConfig {
+    // 可以是 ~/.ssh/config 中的主机别名
     host: String,
+    // 为0时使用SSH配置文件中的端口，否则为22
+    #[serde(default)]
     port: u16,
+    // 为空时使用SSH配置文件中的用户名
+    #[serde(default)]
     username: String,
-    password: String,
+    // 兼容旧的配置：未指定auth_methods时使用密码认证
+    #[serde(default)]
+    password: Option<String>,
+    // 保存在凭据保险库中的密码，连接时再取出
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    password_secret_id: Option<String>,
+    // 按顺序尝试的认证方式
+    #[serde(default)]
+    auth_methods: Vec<SshAuthMethod>,
+    // 非root用户执行特权命令时使用的sudo密码，未设置时使用登录密码
+    #[serde(default)]
+    sudo_password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sudo_password_secret_id: Option<String>,
+    // 连接断开后是否自动重连
+    #[serde(default)]
+    auto_reconnect: bool,
+    // 依次经过的跳板机（类似OpenSSH的ProxyJump），跳板机自身的jump_hosts不生效
+    #[serde(default)]
+    jump_hosts: Vec<SshConnectionConfig>,
+}
+
+impl SshConnectionConfig {
+    // 获取按顺序尝试的认证方式列表
+    fn auth_order(&self) -> Vec<SshAuthMethod> {
+        if !self.auth_methods.is_empty() {
+            return self.auth_methods.clone();
+        }
+
+        if self.password.is_none() && self.password_secret_id.is_none() {
+            return Vec::new();
+        }
+        vec![SshAuthMethod::Password {
+            password: self.password.clone().unwrap_or_default(),
+            password_secret_id: self.password_secret_id.clone(),
+        }]
+    }
+
+    // 配置中的登录密码，用于sudo和keyboard-interactive的密码提示
+    fn login_password(&self) -> Option<&str> {
+        if self.auth_methods.is_empty() {
+            return self.password.as_deref().filter(|password| !password.is_empty());
+        }
+        self.auth_methods.iter().find_map(|method| match method {
+            SshAuthMethod::Password { password, .. } if !password.is_empty() => Some(password.as_str()),
+            _ => None,
+        })
+    }
+}
+
+impl fmt::Debug for SshConnectionConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SshConnectionConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &redacted(self.password.as_ref()))
+            .field("password_secret_id", &self.password_secret_id)
+            .field("auth_methods", &self.auth_methods)
+            .field("sudo_password", &redacted(self.sudo_password.as_ref()))
+            .field("sudo_password_secret_id", &self.sudo_password_secret_id)
+            .field("auto_reconnect", &self.auto_reconnect)
+            .field("jump_hosts", &self.jump_hosts)
+            .finish()
+    }
+}
+
+impl Drop for SshConnectionConfig {
+    fn drop(&mut self) {
+        self.password.zeroize();
+        self.sudo_password.zeroize();
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct FileTransferConfig {
     local_path: String,
     remote_path: String,
+    // 上传完成后比对本地和远程文件的校验和
+    #[serde(default)]
+    verify_checksum: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FileDownloadConfig {
+    remote_path: String,
+    // 本地文件路径，或已存在的本地目录（使用远程文件名）
+    local_path: String,
+    // 保留远程文件的修改时间和权限
+    #[serde(default)]
+    preserve_attributes: bool,
+    // 下载完成后比对本地和远程文件的校验和
+    #[serde(default)]
+    verify_checksum: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,25 +285,90 @@ struct ServiceConfig {
     enable_autostart: bool,
 }
 
+// 应用配置目录，不存在时自动创建
+fn app_config_dir() -> EmsResult<PathBuf> {
+    let dir = dirs::config_dir()
+        .ok_or_else(|| EmsError::NotFound("无法确定配置目录".to_string()))?
+        .join("ems_tools");
+    std::fs::create_dir_all(&dir).map_err(|e| EmsError::Io(format!("无法创建配置目录: {}", e)))?;
+    Ok(dir)
+}
+
 // 初始化日志
 fn main() {
     env_logger::init();
     
     // 初始化SSH会话状态
     let ssh_state = ssh::init_ssh_state();
+    let shell_state = shell::init_shell_state();
+    let exec_state = exec::init_exec_state();
+    let forward_state = forward::init_forward_state();
+    let profile_store = profiles::init_profile_store();
+    let vault_state = vault::init_vault_state();
+    let auth_prompt_state = auth_prompt::init_auth_prompt_state();
+    let recording_state = recording::init_recording_state();
+    let transfer_journal = transfer_journal::init_transfer_journal();
 
     tauri::Builder::default()
         .manage(AppState {
-            log_entries: Mutex::new(Vec::new()),
+            log_entries: Arc::new(Mutex::new(Vec::new())),
         })
         .manage(ssh_state)
+        .manage(shell_state)
+        .manage(exec_state)
+        .manage(forward_state)
+        .manage(profile_store)
+        .manage(vault_state)
+        .manage(auth_prompt_state)
+        .manage(recording_state)
+        .manage(transfer_journal)
         .invoke_handler(tauri::generate_handler![
             ssh::connect,
             ssh::disconnect,
             ssh::execute_terminal_command,
             ssh::get_current_directory,
             ssh::check_connection_status,
+            ssh::list_sessions,
+            known_hosts::accept_host_key,
+            auth_prompt::answer_auth_prompt,
+            complete::complete,
+            recording::start_recording,
+            recording::stop_recording,
+            recording::list_recordings,
+            recording::replay_recording,
+            recording::export_recording,
+            recording::delete_recording,
+            ssh_config::list_ssh_config_hosts,
+            profiles::list_profiles,
+            profiles::add_profile,
+            profiles::update_profile,
+            profiles::delete_profile,
+            profiles::connect_profile,
+            vault::vault_status,
+            vault::init_vault,
+            vault::unlock_vault,
+            vault::lock_vault,
+            vault::list_secrets,
+            vault::store_secret,
+            vault::update_secret,
+            vault::delete_secret,
+            shell::open_shell,
+            shell::write_shell,
+            shell::resize_shell,
+            shell::close_shell,
+            exec::execute_command_stream,
+            exec::cancel_command,
+            forward::start_local_forward,
+            forward::start_remote_forward,
+            forward::start_dynamic_forward,
+            forward::list_forwards,
+            forward::stop_forward,
             file_transfer::upload_file,
+            file_transfer::download_file,
+            dir_transfer::upload_directory,
+            dir_transfer::download_directory,
+            transfer_journal::list_interrupted_transfers,
+            transfer_journal::discard_transfer,
             network::get_network_interfaces,
             network::get_interface_config,
             network::set_interface_config,
@@ -0,0 +1,235 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use serde::Serialize;
+
+use crate::AppState;
+use crate::error::{EmsError, EmsResult};
+use crate::exec::ExecSessions;
+use crate::forward::ForwardSessions;
+use crate::shell::ShellSessions;
+use crate::ssh::{SharedSshSession, SshSessions, connect_ssh};
+use crate::logger::add_log_internal;
+use crate::vault::Vault;
+
+// 健康检查间隔
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+// 自动重连的最大尝试次数
+const MAX_RECONNECT_ATTEMPTS: u32 = 6;
+// 重连退避的最大等待时间
+const MAX_RECONNECT_BACKOFF: Duration = Duration::from_secs(30);
+// 存活探测等待服务器回应的最长时间（毫秒）
+const PROBE_TIMEOUT_MS: u32 = 10_000;
+// LIBSSH2_ERROR_CHANNEL_FAILURE：服务器拒绝打开通道，但已经作出回应
+const LIBSSH2_ERROR_CHANNEL_FAILURE: i32 = -21;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ConnectionStateEvent {
+    session_id: String,
+    // connected / disconnected / reconnecting / failed
+    state: &'static str,
+    attempt: Option<u32>,
+    message: Option<String>,
+}
+
+// 为新建立的会话启动后台健康监控线程
+pub fn spawn_health_monitor(session_id: String, app_handle: AppHandle) {
+    thread::spawn(move || monitor_session(session_id, app_handle));
+}
+
+// 定期探测连接是否存活，连接断开时通知前端并按配置自动重连
+// 会话从注册表中移除（用户断开连接）后线程自动退出
+fn monitor_session(session_id: String, app_handle: AppHandle) {
+    println!("🔧 [MONITOR] 开始监控会话: {}", session_id);
+
+    loop {
+        thread::sleep(HEALTH_CHECK_INTERVAL);
+
+        let Some(ssh_session) = registered_session(&app_handle, &session_id) else {
+            break;
+        };
+
+        let error = match probe_connection(&ssh_session) {
+            Ok(()) => continue,
+            // 会话已被清理，不再监控
+            Err(EmsError::NotConnected) => break,
+            Err(e) => e,
+        };
+
+        let message = format!("SSH连接已断开: {}", error);
+        println!("⚠️ [MONITOR] {} (会话: {})", message, session_id);
+        log_event(&app_handle, "WARN", &format!("{} (会话: {})", message, session_id));
+        emit_state(&app_handle, &session_id, "disconnected", None, Some(message));
+        // 旧连接上的通道都已失效，重连后也无法继续使用
+        close_session_channels(&app_handle, &session_id);
+
+        let auto_reconnect = ssh_session
+            .lock()
+            .ok()
+            .and_then(|ssh_guard| ssh_guard.config.as_ref().map(|config| config.auto_reconnect))
+            .unwrap_or(false);
+
+        if auto_reconnect && reconnect(&session_id, &ssh_session, &app_handle) {
+            continue;
+        }
+
+        // 无法恢复，移除失效的会话，后续命令会返回 NotConnected
+        if let Some(ssh_state) = app_handle.try_state::<SshSessions>() {
+            let _ = ssh_state.remove(&session_id);
+        }
+        if let (Some(vault), Ok(ssh_guard)) = (app_handle.try_state::<Vault>(), ssh_session.lock()) {
+            if let Some(ref config) = ssh_guard.config {
+                vault.release_session_secrets(config);
+            }
+        }
+        let message = if auto_reconnect { "自动重连失败" } else { "SSH连接已断开" };
+        emit_state(&app_handle, &session_id, "failed", None, Some(message.to_string()));
+        break;
+    }
+
+    println!("🔧 [MONITOR] 停止监控会话: {}", session_id);
+}
+
+// 关闭会话上的端口转发，结束Shell和流式命令，前端通过各自的关闭事件得到通知
+fn close_session_channels(app_handle: &AppHandle, session_id: &str) {
+    let forwards = app_handle
+        .try_state::<ForwardSessions>()
+        .and_then(|forward_state| forward_state.close_session_forwards(session_id).ok())
+        .unwrap_or(0);
+    let shells = app_handle
+        .try_state::<ShellSessions>()
+        .map(|shell_state| shell_state.close_session_shells(session_id))
+        .unwrap_or(0);
+    let commands = app_handle
+        .try_state::<ExecSessions>()
+        .map(|exec_state| exec_state.stop_session_commands(session_id))
+        .unwrap_or(0);
+
+    if forwards + shells + commands > 0 {
+        let message = format!(
+            "连接断开，已关闭 {} 个端口转发、{} 个Shell、{} 个流式命令 (会话: {})",
+            forwards, shells, commands, session_id
+        );
+        println!("🔧 [MONITOR] {}", message);
+        log_event(app_handle, "INFO", &message);
+    }
+}
+
+// 会话仍在注册表中时返回该会话
+fn registered_session(app_handle: &AppHandle, session_id: &str) -> Option<SharedSshSession> {
+    app_handle.try_state::<SshSessions>()?.get(session_id).ok()
+}
+
+// 探测连接是否存活：打开并关闭一个通道，必须在超时前收到服务器的回应
+// keepalive_send 只在套接字写入失败时报错，静默丢包的链路要等TCP重传超时才能发现
+fn probe_connection(ssh_session: &SharedSshSession) -> EmsResult<()> {
+    let ssh_guard = ssh_session.lock()?;
+    let session = ssh_guard.session.as_ref().ok_or(EmsError::NotConnected)?;
+
+    let previous_timeout = session.timeout();
+    session.set_timeout(PROBE_TIMEOUT_MS);
+    let result = session.channel_session().map(|mut channel| {
+        let _ = channel.close();
+    });
+    session.set_timeout(previous_timeout);
+
+    match result {
+        Ok(()) => Ok(()),
+        // 服务器限制了通道数量等情况下拒绝打开通道，连接本身是正常的
+        Err(e) if e.code() == ssh2::ErrorCode::Session(LIBSSH2_ERROR_CHANNEL_FAILURE) => Ok(()),
+        // 超时同样视为连接断开
+        Err(e) => Err(e.into()),
+    }
+}
+
+// 第 attempt 次重连前的等待时间：1、2、4、8、16秒，之后不超过30秒
+fn reconnect_backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << (attempt - 1).min(16)).min(MAX_RECONNECT_BACKOFF)
+}
+
+// 按指数退避重连，成功后替换会话中的连接，工作目录等上下文保持不变
+fn reconnect(session_id: &str, ssh_session: &SharedSshSession, app_handle: &AppHandle) -> bool {
+    let Some(config) = ssh_session.lock().ok().and_then(|ssh_guard| ssh_guard.config.clone()) else {
+        return false;
+    };
+
+    for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+        let backoff = reconnect_backoff(attempt);
+        emit_state(app_handle, session_id, "reconnecting", Some(attempt), None);
+        thread::sleep(backoff);
+
+        // 等待期间用户可能已经断开了连接
+        match registered_session(app_handle, session_id) {
+            Some(current) if Arc::ptr_eq(&current, ssh_session) => {}
+            _ => return false,
+        }
+
+        // 保险库中的密码在每次重连时重新取出，保险库已锁定则无法重连
+        let connect_result = match app_handle.try_state::<Vault>() {
+            Some(vault) => vault.resolve_config(&config).and_then(|config| connect_ssh(&config, app_handle)),
+            None => connect_ssh(&config, app_handle),
+        };
+        match connect_result {
+            Ok((session, _)) => {
+                let current_directory = match ssh_session.lock() {
+                    Ok(mut ssh_guard) => {
+                        ssh_guard.session = Some(session);
+                        ssh_guard.current_directory.clone()
+                    }
+                    Err(_) => return false,
+                };
+
+                let message = format!(
+                    "已重新连接到 {}:{} (第 {} 次尝试，工作目录: {})",
+                    config.host, config.port, attempt, current_directory
+                );
+                println!("✅ [MONITOR] {}", message);
+                log_event(app_handle, "INFO", &message);
+                emit_state(app_handle, session_id, "connected", Some(attempt), Some(message));
+                return true;
+            }
+            Err(e) => {
+                let message = format!("第 {} 次重连失败: {}", attempt, e);
+                println!("⚠️ [MONITOR] {}", message);
+                log_event(app_handle, "WARN", &message);
+            }
+        }
+    }
+
+    false
+}
+
+fn emit_state(
+    app_handle: &AppHandle,
+    session_id: &str,
+    state: &'static str,
+    attempt: Option<u32>,
+    message: Option<String>,
+) {
+    let _ = app_handle.emit_all("ssh-connection-state", ConnectionStateEvent {
+        session_id: session_id.to_string(),
+        state,
+        attempt,
+        message,
+    });
+}
+
+fn log_event(app_handle: &AppHandle, level: &str, message: &str) {
+    if let Some(app_state) = app_handle.try_state::<AppState>() {
+        add_log_internal(&app_state, level, message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_up_to_limit() {
+        let schedule: Vec<u64> = (1..=MAX_RECONNECT_ATTEMPTS).map(|attempt| reconnect_backoff(attempt).as_secs()).collect();
+        assert_eq!(schedule, vec![1, 2, 4, 8, 16, 30]);
+        // 增加重连次数时不会溢出
+        assert_eq!(reconnect_backoff(64), MAX_RECONNECT_BACKOFF);
+    }
+}
@@ -1,10 +1,13 @@
-use std::sync::{Arc, Mutex};
 use tauri::State;
 use serde::{Deserialize, Serialize};
 
 use crate::{AppState, NetworkConfig};
-use crate::ssh::{SshSession, execute_command};
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{SshSessions, execute_command, run_blocking, run_command};
+use crate::privilege::Sudo;
+use crate::vault::Vault;
 use crate::logger::add_log_internal;
+use crate::command::{ShellCommand, validate_interface_name, validate_ipv4};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct NetworkInterface {
@@ -17,111 +20,170 @@ pub struct NetworkInterface {
 
 #[tauri::command]
 pub async fn get_network_interfaces(
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
     app_state: State<'_, AppState>,
-) -> Result<Vec<NetworkInterface>, String> {
-    add_log_internal(&app_state, "INFO", "获取网络接口列表");
+) -> EmsResult<Vec<NetworkInterface>> {
+    let ssh_state = ssh_state.get(&session_id)?;
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        add_log_internal(&app_state, "INFO", "获取网络接口列表");
     
-    // 执行命令获取网络接口信息
-    let output = execute_command(&ssh_state, "ip -o addr show")?;
+        // 执行命令获取网络接口信息
+        let output = execute_command(&ssh_state, "ip -o addr show")?;
     
-    // 解析输出
-    let interfaces = parse_ip_addr_output(&output);
+        // 解析输出
+        let interfaces = parse_ip_addr_output(&output);
     
-    add_log_internal(&app_state, "INFO", &format!("找到 {} 个网络接口", interfaces.len()));
+        add_log_internal(&app_state, "INFO", &format!("找到 {} 个网络接口", interfaces.len()));
     
-    Ok(interfaces)
+        Ok(interfaces)
+    }).await
 }
 
 #[tauri::command]
 pub async fn get_interface_config(
     interface: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
     app_state: State<'_, AppState>,
-) -> Result<NetworkConfig, String> {
-    let log_message = format!("获取网络接口 {} 的配置", interface);
-    add_log_internal(&app_state, "INFO", &log_message);
-    
-    // 获取IP地址和子网掩码
-    let ip_cmd = format!("ip -o addr show {} | grep -v 'inet6' | awk '{{print $4}}'", interface);
-    let ip_output = execute_command(&ssh_state, &ip_cmd)?;
-    
-    // 获取默认网关
-    let gateway_cmd = format!("ip route | grep default | grep {} | awk '{{print $3}}'", interface);
-    let gateway_output = execute_command(&ssh_state, &gateway_cmd).unwrap_or_else(|_| "".to_string());
-    
-    // 解析IP地址和子网掩码
-    let ip_parts: Vec<&str> = ip_output.trim().split('/').collect();
-    if ip_parts.len() != 2 {
-        return Err(format!("无法解析接口 {} 的IP配置", interface));
-    }
+) -> EmsResult<NetworkConfig> {
+    validate_interface_name(&interface)?;
+    let ssh_state = ssh_state.get(&session_id)?;
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("获取网络接口 {} 的配置", interface);
+        add_log_internal(&app_state, "INFO", &log_message);
+    
+        // 获取IP地址和子网掩码
+        let ip_cmd = ShellCommand::new("ip").args(["-o", "-4", "addr", "show", "dev"]).arg(&interface).to_string();
+        let ip_output = run_command(&ssh_state, &ip_cmd)?;
+        if !ip_output.success() {
+            return Err(EmsError::CommandFailed {
+                exit_code: ip_output.exit_code,
+                stderr: format!("无法获取接口 {} 的配置: {}", interface, ip_output.stderr.trim()),
+            });
+        }
+
+        // 获取默认网关
+        let gateway_cmd = ShellCommand::new("ip").args(["route", "show", "default", "dev"]).arg(&interface).to_string();
+        let gateway_output = run_command(&ssh_state, &gateway_cmd)?;
+        let gateway_output = if gateway_output.success() {
+            parse_default_gateway(&gateway_output.stdout).unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        // 解析IP地址和子网掩码
+        let ip_info = ip_output.stdout
+            .split_whitespace()
+            .skip_while(|part| *part != "inet")
+            .nth(1)
+            .unwrap_or("");
+        let ip_parts: Vec<&str> = ip_info.split('/').collect();
+        if ip_parts.len() != 2 {
+            return Err(EmsError::ParseError(format!("无法解析接口 {} 的IP配置", interface)));
+        }
     
-    let ip_address = ip_parts[0].to_string();
-    let prefix_len = ip_parts[1].parse::<u8>().map_err(|_| "无法解析子网前缀长度".to_string())?;
-    let netmask = prefix_to_netmask(prefix_len)?;
+        let ip_address = ip_parts[0].to_string();
+        let prefix_len = ip_parts[1].parse::<u8>().map_err(|_| EmsError::ParseError("无法解析子网前缀长度".to_string()))?;
+        let netmask = prefix_to_netmask(prefix_len)?;
     
-    let gateway = if gateway_output.trim().is_empty() {
-        None
-    } else {
-        Some(gateway_output.trim().to_string())
-    };
+        let gateway = if gateway_output.trim().is_empty() {
+            None
+        } else {
+            Some(gateway_output.trim().to_string())
+        };
     
-    let config = NetworkConfig {
-        interface: interface.clone(),
-        ip_address,
-        netmask,
-        gateway,
-    };
+        let config = NetworkConfig {
+            interface: interface.clone(),
+            ip_address,
+            netmask,
+            gateway,
+        };
     
-    add_log_internal(&app_state, "INFO", &format!("成功获取接口 {} 的配置", interface));
+        add_log_internal(&app_state, "INFO", &format!("成功获取接口 {} 的配置", interface));
     
-    Ok(config)
+        Ok(config)
+    }).await
 }
 
 #[tauri::command]
 pub async fn set_interface_config(
     config: NetworkConfig,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let log_message = format!("设置网络接口 {} 的配置", config.interface);
-    add_log_internal(&app_state, "INFO", &log_message);
+) -> EmsResult<String> {
+    // 执行任何命令之前先校验全部参数，避免接口被关闭后才发现参数有误
+    let interface = validate_interface_name(&config.interface)?.to_string();
+    let ip_address = validate_ipv4(&config.ip_address)?;
+    let netmask_prefix = netmask_to_prefix(&config.netmask)?;
+    let gateway = config.gateway
+        .as_deref()
+        .map(str::trim)
+        .filter(|gateway| !gateway.is_empty())
+        .map(validate_ipv4)
+        .transpose()?;
+
+    // 修改网络配置需要管理员权限，非root用户经由sudo执行
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("设置网络接口 {} 的配置", interface);
+        add_log_internal(&app_state, "INFO", &log_message);
     
-    // 先关闭接口
-    let down_cmd = format!("ip link set {} down", config.interface);
-    execute_command(&ssh_state, &down_cmd)?;
+        // 先关闭接口
+        let down_cmd = ShellCommand::new("ip").args(["link", "set"]).arg(&interface).arg("down").to_string();
+        sudo.execute(&down_cmd)?;
     
-    // 设置IP地址和子网掩码
-    let netmask_prefix = netmask_to_prefix(&config.netmask)?;
-    let ip_cmd = format!("ip addr add {}/{} dev {}", 
-        config.ip_address, netmask_prefix, config.interface);
+        // 设置IP地址和子网掩码
+        let ip_cmd = ShellCommand::new("ip")
+            .args(["addr", "add"])
+            .arg(format!("{}/{}", ip_address, netmask_prefix))
+            .arg("dev")
+            .arg(&interface)
+            .to_string();
     
-    // 先清除旧的IP地址
-    let flush_cmd = format!("ip addr flush dev {}", config.interface);
-    execute_command(&ssh_state, &flush_cmd)?;
+        // 先清除旧的IP地址
+        let flush_cmd = ShellCommand::new("ip").args(["addr", "flush", "dev"]).arg(&interface).to_string();
+        sudo.execute(&flush_cmd)?;
     
-    // 设置新的IP地址
-    execute_command(&ssh_state, &ip_cmd)?;
+        // 设置新的IP地址
+        sudo.execute(&ip_cmd)?;
     
-    // 如果提供了网关，则设置默认路由
-    if let Some(gateway) = &config.gateway {
-        // 删除旧的默认路由
-        let del_route_cmd = format!("ip route del default dev {} 2>/dev/null || true", config.interface);
-        execute_command(&ssh_state, &del_route_cmd).ok();
+        // 如果提供了网关，则设置默认路由
+        if let Some(gateway) = &gateway {
+            // 删除旧的默认路由（没有默认路由时会失败，可以忽略）
+            let del_route_cmd = ShellCommand::new("ip").args(["route", "del", "default", "dev"]).arg(&interface).to_string();
+            let del_route_output = sudo.run(&del_route_cmd)?;
+            if !del_route_output.success() {
+                log::debug!("删除旧默认路由失败（可能不存在）: {}", del_route_output.error_message());
+            }
         
-        // 添加新的默认路由
-        let route_cmd = format!("ip route add default via {} dev {}", gateway, config.interface);
-        execute_command(&ssh_state, &route_cmd)?;
-    }
+            // 添加新的默认路由
+            let route_cmd = ShellCommand::new("ip")
+                .args(["route", "add", "default", "via"])
+                .arg(gateway)
+                .arg("dev")
+                .arg(&interface)
+                .to_string();
+            sudo.execute(&route_cmd)?;
+        }
     
-    // 启用接口
-    let up_cmd = format!("ip link set {} up", config.interface);
-    execute_command(&ssh_state, &up_cmd)?;
+        // 启用接口
+        let up_cmd = ShellCommand::new("ip").args(["link", "set"]).arg(&interface).arg("up").to_string();
+        sudo.execute(&up_cmd)?;
     
-    let success_message = format!("成功配置网络接口 {}", config.interface);
-    add_log_internal(&app_state, "INFO", &success_message);
+        let success_message = format!("成功配置网络接口 {}", interface);
+        add_log_internal(&app_state, "INFO", &success_message);
     
-    Ok(success_message)
+        Ok(success_message)
+    }).await
 }
 
 // 辅助函数：解析ip addr命令的输出
@@ -201,10 +263,24 @@ fn parse_ip_addr_output(output: &str) -> Vec<NetworkInterface> {
     interfaces
 }
 
+// 辅助函数：从 ip route show default 的输出中解析网关地址
+fn parse_default_gateway(output: &str) -> Option<String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            parts.iter()
+                .position(|part| *part == "via")
+                .and_then(|i| parts.get(i + 1))
+                .map(|gateway| gateway.to_string())
+        })
+        .next()
+}
+
 // 辅助函数：将CIDR前缀转换为子网掩码
-fn prefix_to_netmask(prefix: u8) -> Result<String, String> {
+fn prefix_to_netmask(prefix: u8) -> EmsResult<String> {
     if prefix > 32 {
-        return Err("无效的前缀长度".to_string());
+        return Err(EmsError::InvalidInput("无效的前缀长度".to_string()));
     }
     
     let mask = !0u32 << (32 - prefix);
@@ -217,15 +293,15 @@ fn prefix_to_netmask(prefix: u8) -> Result<String, String> {
 }
 
 // 辅助函数：将子网掩码转换为CIDR前缀
-fn netmask_to_prefix(netmask: &str) -> Result<u8, String> {
+fn netmask_to_prefix(netmask: &str) -> EmsResult<u8> {
     let parts: Vec<&str> = netmask.split('.').collect();
     if parts.len() != 4 {
-        return Err("无效的子网掩码格式".to_string());
+        return Err(EmsError::InvalidInput("无效的子网掩码格式".to_string()));
     }
     
     let mut prefix = 0;
     for part in parts {
-        let octet = part.parse::<u8>().map_err(|_| "无效的子网掩码值".to_string())?;
+        let octet = part.parse::<u8>().map_err(|_| EmsError::InvalidInput("无效的子网掩码值".to_string()))?;
         
         let mut bits = 0;
         let mut val = octet;
@@ -0,0 +1,240 @@
+use serde::Serialize;
+use zeroize::Zeroizing;
+
+use crate::command::ShellCommand;
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{CommandOutput, SharedSshSession, run_command, run_command_with_input};
+use crate::vault::Vault;
+
+// 连接后检测登录用户的权限、家目录和命令搜索路径
+const DETECT_COMMAND: &str = "id -u; printf '%s\\n' \"$HOME\"; \
+    if ! command -v sudo >/dev/null 2>&1; then echo none; \
+    elif sudo -n true >/dev/null 2>&1; then echo nopasswd; \
+    else echo passwd; fi; \
+    printf '%s\\n' \"$PATH\"";
+// sudo -S 的密码提示，密码被拒绝时会再次出现，据此判断密码错误而不依赖sudo的提示语言
+const SUDO_PROMPT: &str = "[ems_tools-sudo-password]";
+// sudo 认证失败时的退出代码
+const SUDO_AUTH_FAILED: i32 = 1;
+// 没有获取到 $PATH 时使用的默认值
+const DEFAULT_SEARCH_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+// 登录用户执行特权命令的方式
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Privilege {
+    // 以root登录，直接执行
+    Root,
+    // 免密sudo
+    PasswordlessSudo,
+    // sudo需要密码，通过 sudo -S 从stdin提供
+    PasswordSudo,
+    // 没有sudo
+    NoSudo,
+    // 检测失败
+    Unknown,
+}
+
+// 连接时检测到的远程环境
+#[derive(Debug, Clone, Serialize)]
+pub struct RemoteEnvironment {
+    pub privilege: Privilege,
+    pub home_directory: String,
+    // $PATH 中的目录，用于命令补全
+    pub search_path: Vec<String>,
+}
+
+impl Default for RemoteEnvironment {
+    fn default() -> Self {
+        RemoteEnvironment {
+            privilege: Privilege::Unknown,
+            home_directory: "/".to_string(),
+            search_path: split_search_path(DEFAULT_SEARCH_PATH),
+        }
+    }
+}
+
+// 检测是否为root、sudo是否免密以及真实的 $HOME
+pub fn detect_environment(ssh_session: &SharedSshSession) -> EmsResult<RemoteEnvironment> {
+    let output = run_command(ssh_session, DETECT_COMMAND)?;
+    if !output.success() {
+        return Err(output.to_error());
+    }
+    parse_environment(&output.stdout)
+}
+
+fn parse_environment(stdout: &str) -> EmsResult<RemoteEnvironment> {
+    let mut lines = stdout.lines().map(str::trim);
+    let (Some(uid), Some(home), Some(sudo)) = (lines.next(), lines.next(), lines.next()) else {
+        return Err(EmsError::ParseError(format!("无法解析远程环境信息: {}", stdout.trim())));
+    };
+
+    let privilege = match (uid, sudo) {
+        ("0", _) => Privilege::Root,
+        (_, "nopasswd") => Privilege::PasswordlessSudo,
+        (_, "passwd") => Privilege::PasswordSudo,
+        (_, "none") => Privilege::NoSudo,
+        _ => Privilege::Unknown,
+    };
+    let home_directory = if home.starts_with('/') { home } else { "/" };
+    let search_path = lines.next().filter(|path| !path.is_empty()).unwrap_or(DEFAULT_SEARCH_PATH);
+
+    Ok(RemoteEnvironment {
+        privilege,
+        home_directory: home_directory.to_string(),
+        search_path: split_search_path(search_path),
+    })
+}
+
+// 只保留绝对路径，去掉重复项
+fn split_search_path(path: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = Vec::new();
+    for dir in path.split(':').filter(|dir| dir.starts_with('/')) {
+        if !dirs.iter().any(|existing| existing == dir) {
+            dirs.push(dir.to_string());
+        }
+    }
+    dirs
+}
+
+// 以管理员权限执行命令：root直接执行，否则经由sudo
+// 需要密码时通过 sudo -S 从stdin提供，密码不会出现在命令行和日志中
+pub struct Sudo {
+    ssh_session: SharedSshSession,
+    vault: Vault,
+}
+
+impl Sudo {
+    pub fn new(ssh_session: SharedSshSession, vault: Vault) -> Self {
+        Sudo { ssh_session, vault }
+    }
+
+    // 执行命令，返回stdout、stderr和退出代码
+    pub fn run(&self, command: &str) -> EmsResult<CommandOutput> {
+        let privilege = self.ssh_session.lock()?.environment.privilege;
+        match privilege {
+            Privilege::Root => run_command(&self.ssh_session, command),
+            Privilege::PasswordlessSudo => {
+                run_command(&self.ssh_session, &sudo_command(&["-n"], command))
+            }
+            Privilege::NoSudo => Err(EmsError::InvalidInput(
+                "当前用户不是root且没有安装sudo，无法执行需要管理员权限的操作".to_string(),
+            )),
+            Privilege::PasswordSudo | Privilege::Unknown => match self.password()? {
+                Some(password) => self.run_with_password(command, &password),
+                // 检测失败且没有密码时按免密sudo尝试，-n 保证不会等待输入
+                None if privilege == Privilege::Unknown => {
+                    run_command(&self.ssh_session, &sudo_command(&["-n"], command))
+                }
+                None => Err(EmsError::AuthFailed(
+                    "sudo需要密码，请在连接配置中设置sudo密码或登录密码".to_string(),
+                )),
+            },
+        }
+    }
+
+    // 执行命令，退出代码非0时返回错误
+    pub fn execute(&self, command: &str) -> EmsResult<String> {
+        let output = self.run(command)?;
+        if !output.success() {
+            return Err(output.to_error());
+        }
+        Ok(output.stdout)
+    }
+
+    fn run_with_password(&self, command: &str, password: &str) -> EmsResult<CommandOutput> {
+        // -k 忽略已缓存的凭据，sudo 总是从stdin读取一行密码
+        let sudo_command = sudo_command(&["-k", "-S", "-p", SUDO_PROMPT], command);
+        let mut input = Zeroizing::new(String::with_capacity(password.len() + 1));
+        input.push_str(password);
+        input.push('\n');
+
+        let mut output = run_command_with_input(&self.ssh_session, &sudo_command, Some(input.as_bytes()))?;
+        if password_rejected(&mut output) {
+            return Err(EmsError::AuthFailed("sudo密码错误".to_string()));
+        }
+        Ok(output)
+    }
+
+    // sudo密码：优先使用保存的sudo密码，否则使用登录密码
+    fn password(&self) -> EmsResult<Option<Zeroizing<String>>> {
+        let config = self.ssh_session.lock()?.config.clone().ok_or(EmsError::NotConnected)?;
+
+        if let Some(ref password) = config.sudo_password {
+            if !password.is_empty() {
+                return Ok(Some(Zeroizing::new(password.clone())));
+            }
+        }
+        if let Some(ref id) = config.sudo_password_secret_id {
+            return self.vault.reveal(id).map(Some);
+        }
+
+        let resolved = self.vault.resolve_config(&config)?;
+        Ok(resolved.login_password().map(|password| Zeroizing::new(password.to_string())))
+    }
+}
+
+// 从stderr中去掉密码提示，返回密码是否被拒绝
+// 密码错误时sudo会再次提示，读到EOF后以退出代码1失败
+fn password_rejected(output: &mut CommandOutput) -> bool {
+    let prompts = output.stderr.matches(SUDO_PROMPT).count();
+    output.stderr = output.stderr.replace(SUDO_PROMPT, "");
+    output.exit_code == Some(SUDO_AUTH_FAILED) && prompts > 1
+}
+
+// 整条命令交给 sh -c，管道和重定向同样以管理员权限执行
+// 命令的stdin重定向到 /dev/null：免密（NOPASSWD）时sudo不读取stdin，密码不能落到命令的输入中
+fn sudo_command(options: &[&str], command: &str) -> String {
+    let command = format!("exec </dev/null; {}", command);
+    ShellCommand::new("sudo").args(options).args(["sh", "-c", &command]).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn output(stderr: &str, exit_code: i32) -> CommandOutput {
+        CommandOutput {
+            stdout: String::new(),
+            stderr: stderr.to_string(),
+            exit_code: Some(exit_code),
+            duration: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn detects_rejected_sudo_password_by_prompt_count() {
+        // 提示语言不影响判断
+        let mut rejected = output(&format!("{0}Sorry, try again.\n{0}sudo: 需要密码\n", SUDO_PROMPT), 1);
+        assert!(password_rejected(&mut rejected));
+        assert!(!rejected.stderr.contains(SUDO_PROMPT));
+
+        // 密码正确，命令本身以1退出
+        let mut command_failed = output(&format!("{}grep: no match\n", SUDO_PROMPT), 1);
+        assert!(!password_rejected(&mut command_failed));
+        assert_eq!(command_failed.stderr, "grep: no match\n");
+    }
+
+    // 用不读取stdin的 sudo 函数模拟凭据已缓存或免密的情况，在本地shell中执行生成的命令
+    #[cfg(unix)]
+    #[test]
+    fn password_never_reaches_command_stdin() {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let command = sudo_command(&["-k", "-S", "-p", SUDO_PROMPT], "cat; echo done");
+        assert!(command.starts_with("sudo -k -S "));
+
+        let script = format!("sudo() {{ while [ \"$1\" != sh ]; do shift; done; \"$@\"; }}; {}", command);
+        let mut child = Command::new("sh")
+            .args(["-c", &script])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(b"secret\n").unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "done\n");
+    }
+}
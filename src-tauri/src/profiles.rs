@@ -0,0 +1,378 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use chrono::Local;
+use tauri::{AppHandle, State};
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, SshAuthMethod, SshConnectionConfig, app_config_dir};
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{ConnectResult, SshSessions, open_session};
+use crate::logger::add_log_internal;
+use crate::vault::{Vault, secret_ids};
+
+// 保存的连接配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ConnectionProfile {
+    id: String,
+    name: String,
+    // 分组，例如站点或客户
+    #[serde(default)]
+    group: Option<String>,
+    // 标签，例如设备类型
+    #[serde(default)]
+    tags: Vec<String>,
+    config: SshConnectionConfig,
+    created_at: String,
+    updated_at: String,
+    #[serde(default)]
+    last_used: Option<String>,
+}
+
+impl ConnectionProfile {
+    pub fn config(&self) -> &SshConnectionConfig {
+        &self.config
+    }
+}
+
+// 新建或修改连接配置时前端传入的内容
+#[derive(Debug, Deserialize)]
+pub struct ProfileInput {
+    name: String,
+    #[serde(default)]
+    group: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    // 修改时可以省略，只更新名称、分组和标签
+    #[serde(default)]
+    config: Option<SshConnectionConfig>,
+}
+
+// 返回给前端的连接配置，不包含密码等敏感信息
+#[derive(Debug, Serialize)]
+pub struct ProfileSummary {
+    id: String,
+    name: String,
+    group: Option<String>,
+    tags: Vec<String>,
+    host: String,
+    port: u16,
+    username: String,
+    auth_methods: Vec<&'static str>,
+    // user@host:port 格式
+    jump_hosts: Vec<String>,
+    auto_reconnect: bool,
+    created_at: String,
+    updated_at: String,
+    last_used: Option<String>,
+}
+
+impl From<&ConnectionProfile> for ProfileSummary {
+    fn from(profile: &ConnectionProfile) -> Self {
+        let config = &profile.config;
+        ProfileSummary {
+            id: profile.id.clone(),
+            name: profile.name.clone(),
+            group: profile.group.clone(),
+            tags: profile.tags.clone(),
+            host: config.host.clone(),
+            port: config.port,
+            username: config.username.clone(),
+            auth_methods: config.auth_order().iter().map(|method| method.name()).collect(),
+            jump_hosts: config.jump_hosts
+                .iter()
+                .map(|hop| format!("{}@{}:{}", hop.username, hop.host, hop.port))
+                .collect(),
+            auto_reconnect: config.auto_reconnect,
+            created_at: profile.created_at.clone(),
+            updated_at: profile.updated_at.clone(),
+            last_used: profile.last_used.clone(),
+        }
+    }
+}
+
+// 连接配置存储，保存在配置目录下的 profiles.json
+pub struct ProfileStore {
+    profiles: Mutex<Vec<ConnectionProfile>>,
+}
+
+impl ProfileStore {
+    // 从文件加载，文件不存在或损坏时从空列表开始
+    fn load() -> Self {
+        let profiles = match read_profiles() {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                println!("⚠️ [PROFILE] 读取连接配置失败: {}", e);
+                Vec::new()
+            }
+        };
+        ProfileStore {
+            profiles: Mutex::new(profiles),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> EmsResult<ConnectionProfile> {
+        let profiles = self.profiles.lock()?;
+        profiles
+            .iter()
+            .find(|profile| profile.id == id)
+            .cloned()
+            .ok_or_else(|| EmsError::NotFound(format!("连接配置不存在: {}", id)))
+    }
+
+    // 修改配置列表并写回文件，写入失败时内存中的修改不生效
+    fn update<T>(&self, apply: impl FnOnce(&mut Vec<ConnectionProfile>) -> EmsResult<T>) -> EmsResult<T> {
+        let mut profiles = self.profiles.lock()?;
+        let mut updated = profiles.clone();
+        let result = apply(&mut updated)?;
+        write_profiles(&updated)?;
+        *profiles = updated;
+        Ok(result)
+    }
+}
+
+// 初始化连接配置存储
+pub fn init_profile_store() -> ProfileStore {
+    ProfileStore::load()
+}
+
+fn profiles_path() -> EmsResult<PathBuf> {
+    Ok(app_config_dir()?.join("profiles.json"))
+}
+
+fn read_profiles() -> EmsResult<Vec<ConnectionProfile>> {
+    let path = profiles_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+// 先写临时文件再重命名，避免写入中断导致文件损坏
+fn write_profiles(profiles: &[ConnectionProfile]) -> EmsResult<()> {
+    let path = profiles_path()?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(profiles)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+fn now() -> String {
+    Local::now().to_rfc3339()
+}
+
+// 去掉空白和重复的分组、标签
+fn normalize(input: &mut ProfileInput) -> EmsResult<()> {
+    input.name = input.name.trim().to_string();
+    if input.name.is_empty() {
+        return Err(EmsError::InvalidInput("连接配置名称不能为空".to_string()));
+    }
+    input.group = input.group
+        .as_ref()
+        .map(|group| group.trim().to_string())
+        .filter(|group| !group.is_empty());
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.tags.iter().map(|tag| tag.trim()) {
+        if !tag.is_empty() && !tags.iter().any(|existing| existing == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    input.tags = tags;
+    Ok(())
+}
+
+// 修改时前端不会回传密码，未填写的密码沿用原配置引用的凭据
+fn keep_secrets(config: &mut SshConnectionConfig, existing: &SshConnectionConfig) {
+    if config.password.as_ref().is_none_or(|password| password.is_empty()) && config.password_secret_id.is_none() {
+        config.password_secret_id = existing.password_secret_id.clone();
+    }
+    if config.sudo_password.as_ref().is_none_or(|password| password.is_empty()) && config.sudo_password_secret_id.is_none() {
+        config.sudo_password_secret_id = existing.sudo_password_secret_id.clone();
+    }
+    if config.auth_methods.is_empty() {
+        config.auth_methods = existing.auth_methods.clone();
+    }
+    for method in &mut config.auth_methods {
+        let previous = existing.auth_methods.iter().find(|old| old.name() == method.name());
+        match (method, previous) {
+            (
+                SshAuthMethod::Password { password, password_secret_id: id @ None },
+                Some(SshAuthMethod::Password { password_secret_id: previous_id, .. }),
+            ) if password.is_empty() => {
+                *id = previous_id.clone();
+            }
+            (
+                SshAuthMethod::KeyFile { passphrase: None, passphrase_secret_id: id @ None, .. },
+                Some(SshAuthMethod::KeyFile { passphrase_secret_id: previous_id, .. }),
+            ) => {
+                *id = previous_id.clone();
+            }
+            (
+                SshAuthMethod::KeyMemory { private_key, private_key_secret_id, passphrase, passphrase_secret_id },
+                Some(SshAuthMethod::KeyMemory { private_key_secret_id: previous_key_id, passphrase_secret_id: previous_passphrase_id, .. }),
+            ) => {
+                if private_key.is_empty() && private_key_secret_id.is_none() {
+                    *private_key_secret_id = previous_key_id.clone();
+                }
+                if passphrase.is_none() && passphrase_secret_id.is_none() {
+                    *passphrase_secret_id = previous_passphrase_id.clone();
+                }
+            }
+            _ => {}
+        }
+    }
+    for hop in &mut config.jump_hosts {
+        let previous = existing.jump_hosts
+            .iter()
+            .find(|old| old.host == hop.host && old.port == hop.port && old.username == hop.username);
+        if let Some(previous) = previous {
+            keep_secrets(hop, previous);
+        }
+    }
+}
+
+// 列出所有连接配置
+#[tauri::command]
+pub async fn list_profiles(
+    profile_store: State<'_, ProfileStore>,
+) -> EmsResult<Vec<ProfileSummary>> {
+    let profiles = profile_store.profiles.lock()?;
+    Ok(profiles.iter().map(ProfileSummary::from).collect())
+}
+
+// 新建连接配置，密码和私钥口令存入凭据保险库
+#[tauri::command]
+pub async fn add_profile(
+    mut profile: ProfileInput,
+    profile_store: State<'_, ProfileStore>,
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<ProfileSummary> {
+    normalize(&mut profile)?;
+    let mut config = profile.config
+        .take()
+        .ok_or_else(|| EmsError::InvalidInput("缺少连接参数".to_string()))?;
+    vault.seal_config(&mut config, &profile.name)?;
+
+    let timestamp = now();
+    let profile = ConnectionProfile {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: profile.name,
+        group: profile.group,
+        tags: profile.tags,
+        config,
+        created_at: timestamp.clone(),
+        updated_at: timestamp,
+        last_used: None,
+    };
+    let summary = ProfileSummary::from(&profile);
+
+    profile_store.update(|profiles| {
+        profiles.push(profile);
+        Ok(())
+    })?;
+
+    add_log_internal(&app_state, "INFO", &format!("新建连接配置: {}", summary.name));
+    Ok(summary)
+}
+
+// 修改连接配置
+#[tauri::command]
+pub async fn update_profile(
+    id: String,
+    mut profile: ProfileInput,
+    profile_store: State<'_, ProfileStore>,
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<ProfileSummary> {
+    normalize(&mut profile)?;
+
+    let mut unused_secrets = Vec::new();
+    let summary = profile_store.update(|profiles| {
+        let existing = profiles
+            .iter_mut()
+            .find(|existing| existing.id == id)
+            .ok_or_else(|| EmsError::NotFound(format!("连接配置不存在: {}", id)))?;
+
+        if let Some(mut config) = profile.config.take() {
+            keep_secrets(&mut config, &existing.config);
+            vault.seal_config(&mut config, &profile.name)?;
+            let kept = secret_ids(&config);
+            unused_secrets = secret_ids(&existing.config)
+                .into_iter()
+                .filter(|id| !kept.contains(id))
+                .collect();
+            existing.config = config;
+        }
+
+        existing.name = profile.name;
+        existing.group = profile.group;
+        existing.tags = profile.tags;
+        existing.updated_at = now();
+        Ok(ProfileSummary::from(&*existing))
+    })?;
+
+    // 不再引用的凭据从保险库删除，失败不影响修改结果
+    if let Err(e) = vault.remove(&unused_secrets) {
+        println!("⚠️ [PROFILE] 删除不再使用的凭据失败: {}", e);
+    }
+
+    add_log_internal(&app_state, "INFO", &format!("修改连接配置: {}", summary.name));
+    Ok(summary)
+}
+
+// 删除连接配置
+#[tauri::command]
+pub async fn delete_profile(
+    id: String,
+    profile_store: State<'_, ProfileStore>,
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    let profile = profile_store.update(|profiles| {
+        let index = profiles
+            .iter()
+            .position(|profile| profile.id == id)
+            .ok_or_else(|| EmsError::NotFound(format!("连接配置不存在: {}", id)))?;
+        Ok(profiles.remove(index))
+    })?;
+    let name = profile.name.clone();
+
+    // 同时删除该配置引用的凭据
+    if let Err(e) = vault.remove(&secret_ids(&profile.config)) {
+        println!("⚠️ [PROFILE] 删除连接配置的凭据失败: {}", e);
+    }
+
+    add_log_internal(&app_state, "INFO", &format!("删除连接配置: {}", name));
+    Ok(())
+}
+
+// 使用保存的连接配置建立连接，密码不经过前端
+#[tauri::command]
+pub async fn connect_profile(
+    id: String,
+    app_handle: AppHandle,
+    ssh_state: State<'_, SshSessions>,
+    profile_store: State<'_, ProfileStore>,
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<ConnectResult> {
+    let profile = profile_store.get(&id)?;
+    add_log_internal(&app_state, "INFO", &format!("使用连接配置: {}", profile.name));
+
+    let result = open_session(profile.config, app_handle, &ssh_state, &vault, &app_state).await?;
+
+    // 记录最近使用时间，失败不影响连接
+    let recorded = profile_store.update(|profiles| {
+        if let Some(profile) = profiles.iter_mut().find(|profile| profile.id == id) {
+            profile.last_used = Some(now());
+        }
+        Ok(())
+    });
+    if let Err(e) = recorded {
+        println!("⚠️ [PROFILE] 更新最近使用时间失败: {}", e);
+    }
+
+    Ok(result)
+}
@@ -0,0 +1,565 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::State;
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, app_config_dir};
+use crate::error::{EmsError, EmsResult};
+use crate::logger::add_log_internal;
+use crate::shell::ShellSessions;
+use crate::ssh::SshSessions;
+
+// 非PTY终端没有尺寸，按常见的默认值录制
+const DEFAULT_WIDTH: u32 = 120;
+const DEFAULT_HEIGHT: u32 = 30;
+
+// 录制的来源
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingKind {
+    // execute_terminal_command 执行的命令
+    Terminal,
+    // 交互式PTY Shell
+    Shell,
+}
+
+// asciicast v2 文件头
+// host/port/username/session_id/kind/capture_input 是附加字段，播放器会忽略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CastHeader {
+    version: u32,
+    width: u32,
+    height: u32,
+    timestamp: i64,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    env: HashMap<String, String>,
+    #[serde(default)]
+    host: String,
+    #[serde(default)]
+    port: u16,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    session_id: String,
+    #[serde(default = "default_kind")]
+    kind: RecordingKind,
+    // 是否录制了键盘输入
+    #[serde(default)]
+    capture_input: bool,
+}
+
+fn default_kind() -> RecordingKind {
+    RecordingKind::Terminal
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecordingInfo {
+    recording_id: String,
+    title: String,
+    host: String,
+    port: u16,
+    username: String,
+    kind: RecordingKind,
+    capture_input: bool,
+    // 开始时间（Unix时间戳，秒）
+    started_at: i64,
+    duration_secs: f64,
+    size: u64,
+    // 是否仍在录制
+    active: bool,
+}
+
+// 一个事件：[时间, 类型, 数据]，类型为 o(输出)、i(输入)、r(尺寸变化)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CastEvent(f64, String, String);
+
+#[derive(Debug, Serialize)]
+pub struct RecordingData {
+    info: RecordingInfo,
+    width: u32,
+    height: u32,
+    events: Vec<CastEvent>,
+}
+
+// 正在写入的录制文件，每个事件写入后立即刷新，程序异常退出时已录制的内容不会丢失
+pub struct Recorder {
+    recording_id: String,
+    // 键盘输入可能包含密码（sudo、passwd 等不回显的输入），只在开始录制时明确选择后才写入
+    capture_input: bool,
+    started: Instant,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl Recorder {
+    fn create(recording_id: String, header: &CastHeader) -> EmsResult<Self> {
+        let path = recording_path(&recording_id)?;
+        Self::create_at(&path, recording_id, header)
+    }
+
+    fn create_at(path: &Path, recording_id: String, header: &CastHeader) -> EmsResult<Self> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", serde_json::to_string(header)?)?;
+        writer.flush()?;
+
+        Ok(Recorder {
+            recording_id,
+            capture_input: header.capture_input,
+            started: Instant::now(),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    pub fn output(&self, data: &str) {
+        self.write_event("o", data);
+    }
+
+    pub fn input(&self, data: &str) {
+        if self.capture_input {
+            self.write_event("i", data);
+        }
+    }
+
+    pub fn resize(&self, cols: u32, rows: u32) {
+        self.write_event("r", &format!("{}x{}", cols, rows));
+    }
+
+    // 录制失败不影响终端本身的使用
+    fn write_event(&self, code: &str, data: &str) {
+        if data.is_empty() {
+            return;
+        }
+        let event = CastEvent(self.started.elapsed().as_secs_f64(), code.to_string(), data.to_string());
+        let result = serde_json::to_string(&event)
+            .map_err(EmsError::from)
+            .and_then(|line| {
+                let mut writer = self.writer.lock()?;
+                writeln!(writer, "{}", line)?;
+                writer.flush()?;
+                Ok(())
+            });
+        if let Err(e) = result {
+            println!("⚠️ [RECORDING] 写入录制 {} 失败: {}", self.recording_id, e);
+        }
+    }
+}
+
+// 正在进行的录制，按会话ID（终端）或Shell ID索引
+pub struct Recordings {
+    active: Mutex<HashMap<String, Arc<Recorder>>>,
+}
+
+impl Recordings {
+    pub fn new() -> Self {
+        Recordings {
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, target_id: &str) -> Option<Arc<Recorder>> {
+        self.active.lock().ok().and_then(|active| active.get(target_id).cloned())
+    }
+
+    // 停止录制，返回录制ID
+    pub fn stop(&self, target_id: &str) -> Option<String> {
+        let recorder = self.active.lock().ok()?.remove(target_id)?;
+        println!("✅ [RECORDING] 录制已结束: {}", recorder.recording_id);
+        Some(recorder.recording_id.clone())
+    }
+
+    fn is_active(&self, recording_id: &str) -> bool {
+        self.active
+            .lock()
+            .map(|active| active.values().any(|recorder| recorder.recording_id == recording_id))
+            .unwrap_or(false)
+    }
+}
+
+// 初始化录制状态
+pub fn init_recording_state() -> Recordings {
+    Recordings::new()
+}
+
+fn recordings_dir() -> EmsResult<PathBuf> {
+    let dir = app_config_dir()?.join("recordings");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+// 录制ID是UUID，校验后再拼接路径
+fn recording_path(recording_id: &str) -> EmsResult<PathBuf> {
+    uuid::Uuid::parse_str(recording_id)
+        .map_err(|_| EmsError::InvalidInput(format!("无效的录制ID: {}", recording_id)))?;
+    Ok(recordings_dir()?.join(format!("{}.cast", recording_id)))
+}
+
+// 非PTY终端的输出只有 \n，回放时需要 \r\n 才能回到行首
+fn terminal_text(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\n', "\r\n")
+}
+
+// 录制一条终端命令：提示符和命令作为输出回显，命令本身记为输入
+pub fn record_terminal_command(recorder: &Recorder, directory: &str, command: &str, result: &EmsResult<String>) {
+    recorder.input(&format!("{}\n", command));
+    recorder.output(&format!("{}$ {}\r\n", directory, command));
+    let output = match result {
+        Ok(output) => terminal_text(output),
+        Err(EmsError::CommandFailed { stderr, .. }) => terminal_text(stderr),
+        Err(e) => format!("{}\r\n", e),
+    };
+    recorder.output(&output);
+}
+
+// 开始录制终端（不指定 shell_id）或交互式Shell
+// 默认只录制输出，capture_input 为 true 时同时录制键盘输入
+#[tauri::command]
+pub async fn start_recording(
+    session_id: String,
+    shell_id: Option<String>,
+    capture_input: Option<bool>,
+    ssh_state: State<'_, SshSessions>,
+    shell_state: State<'_, ShellSessions>,
+    recordings: State<'_, Recordings>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<String> {
+    let ssh_session = ssh_state.get(&session_id)?;
+    let config = ssh_session.lock()?.config.clone().ok_or(EmsError::NotConnected)?;
+
+    let (target_id, kind, (width, height)) = match shell_id {
+        Some(shell_id) => {
+            let size = shell_state.get(&shell_id)?.size();
+            (shell_id, RecordingKind::Shell, size)
+        }
+        None => (session_id.clone(), RecordingKind::Terminal, (DEFAULT_WIDTH, DEFAULT_HEIGHT)),
+    };
+    if recordings.get(&target_id).is_some() {
+        return Err(EmsError::InvalidInput("该终端已在录制中".to_string()));
+    }
+
+    let header = CastHeader {
+        version: 2,
+        width,
+        height,
+        timestamp: chrono::Utc::now().timestamp(),
+        title: format!("{}@{}:{}", config.username, config.host, config.port),
+        env: HashMap::from([("TERM".to_string(), "xterm-256color".to_string())]),
+        host: config.host.clone(),
+        port: config.port,
+        username: config.username.clone(),
+        session_id,
+        kind,
+        capture_input: capture_input.unwrap_or(false),
+    };
+    let recording_id = uuid::Uuid::new_v4().to_string();
+    let recorder = Recorder::create(recording_id.clone(), &header)?;
+    recordings.active.lock()?.insert(target_id, Arc::new(recorder));
+
+    let log_message = if header.capture_input {
+        format!("开始录制 {}（包含键盘输入）: {}", header.title, recording_id)
+    } else {
+        format!("开始录制 {}: {}", header.title, recording_id)
+    };
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("✅ [RECORDING] {}", log_message);
+
+    Ok(recording_id)
+}
+
+// 停止录制，target_id 为开始录制时的会话ID或Shell ID
+#[tauri::command]
+pub async fn stop_recording(
+    target_id: String,
+    recordings: State<'_, Recordings>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<String> {
+    let recording_id = recordings
+        .stop(&target_id)
+        .ok_or_else(|| EmsError::NotFound("该终端没有进行中的录制".to_string()))?;
+    add_log_internal(&app_state, "INFO", &format!("停止录制: {}", recording_id));
+    Ok(recording_id)
+}
+
+// 列出所有录制，最新的在前
+#[tauri::command]
+pub async fn list_recordings(recordings: State<'_, Recordings>) -> EmsResult<Vec<RecordingInfo>> {
+    let mut infos = Vec::new();
+    for entry in fs::read_dir(recordings_dir()?)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("cast") {
+            continue;
+        }
+        let Some(recording_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        match read_recording(recording_id, &recordings) {
+            Ok((info, _, _)) => infos.push(info),
+            Err(e) => println!("⚠️ [RECORDING] 跳过无法读取的录制 {}: {}", path.display(), e),
+        }
+    }
+    infos.sort_by_key(|info| std::cmp::Reverse(info.started_at));
+    Ok(infos)
+}
+
+// 读取录制的全部事件，由前端按时间回放
+#[tauri::command]
+pub async fn replay_recording(
+    recording_id: String,
+    recordings: State<'_, Recordings>,
+) -> EmsResult<RecordingData> {
+    let (info, header, events) = read_recording(&recording_id, &recordings)?;
+    Ok(RecordingData {
+        info,
+        width: header.width,
+        height: header.height,
+        events,
+    })
+}
+
+// 把录制导出为 .cast 文件，可以用 asciinema play 播放
+// 目标文件已存在时返回错误，overwrite 为 true 时才覆盖
+#[tauri::command]
+pub async fn export_recording(
+    recording_id: String,
+    destination: String,
+    overwrite: Option<bool>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<String> {
+    let source = recording_path(&recording_id)?;
+    if !source.exists() {
+        return Err(EmsError::NotFound(format!("录制不存在: {}", recording_id)));
+    }
+    copy_recording(&source, Path::new(&destination), overwrite.unwrap_or(false))?;
+
+    let log_message = format!("导出录制 {} 到 {}", recording_id, destination);
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("✅ [RECORDING] {}", log_message);
+    Ok(destination)
+}
+
+#[tauri::command]
+pub async fn delete_recording(
+    recording_id: String,
+    recordings: State<'_, Recordings>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    if recordings.is_active(&recording_id) {
+        return Err(EmsError::InvalidInput("录制进行中，请先停止录制".to_string()));
+    }
+    fs::remove_file(recording_path(&recording_id)?)?;
+    add_log_internal(&app_state, "INFO", &format!("删除录制: {}", recording_id));
+    Ok(())
+}
+
+// 不覆盖时以 create_new 打开目标，检查和创建之间不会被其他文件抢占
+fn copy_recording(source: &Path, destination: &Path, overwrite: bool) -> EmsResult<()> {
+    if destination.canonicalize().ok() == Some(source.canonicalize()?) {
+        return Err(EmsError::InvalidInput("不能导出到录制文件本身".to_string()));
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    let mut target = options.open(destination).map_err(|e| match e.kind() {
+        ErrorKind::AlreadyExists => EmsError::InvalidInput(format!("目标文件已存在: {}", destination.display())),
+        _ => EmsError::from(e),
+    })?;
+    io::copy(&mut File::open(source)?, &mut target)?;
+    Ok(())
+}
+
+fn read_recording(
+    recording_id: &str,
+    recordings: &Recordings,
+) -> EmsResult<(RecordingInfo, CastHeader, Vec<CastEvent>)> {
+    let path = recording_path(recording_id)?;
+    let size = fs::metadata(&path)?.len();
+    let (header, events) = read_cast(&path)?;
+
+    let info = RecordingInfo {
+        recording_id: recording_id.to_string(),
+        title: header.title.clone(),
+        host: header.host.clone(),
+        port: header.port,
+        username: header.username.clone(),
+        kind: header.kind,
+        capture_input: header.capture_input,
+        started_at: header.timestamp,
+        duration_secs: events.last().map(|event| event.0).unwrap_or(0.0),
+        size,
+        active: recordings.is_active(recording_id),
+    };
+    Ok((info, header, events))
+}
+
+// 读取 .cast 文件的文件头和事件
+fn read_cast(path: &Path) -> EmsResult<(CastHeader, Vec<CastEvent>)> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+
+    let header_line = lines
+        .next()
+        .ok_or_else(|| EmsError::ParseError(format!("录制文件为空: {}", path.display())))??;
+    let header: CastHeader = serde_json::from_str(&header_line)?;
+    if header.version != 2 {
+        return Err(EmsError::ParseError(format!("不支持的录制格式版本: {}", header.version)));
+    }
+
+    let mut events = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        // 程序异常退出时最后一行可能不完整
+        match serde_json::from_str::<CastEvent>(&line) {
+            Ok(event) => events.push(event),
+            Err(_) => break,
+        }
+    }
+    Ok((header, events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_dir() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("ems-recording-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn test_header(capture_input: bool) -> CastHeader {
+        CastHeader {
+            version: 2,
+            width: 80,
+            height: 24,
+            timestamp: 1_700_000_000,
+            title: "root@10.0.0.5:22".to_string(),
+            env: HashMap::from([("TERM".to_string(), "xterm-256color".to_string())]),
+            host: "10.0.0.5".to_string(),
+            port: 22,
+            username: "root".to_string(),
+            session_id: "session-1".to_string(),
+            kind: RecordingKind::Shell,
+            capture_input,
+        }
+    }
+
+    fn record(dir: &Path, capture_input: bool) -> PathBuf {
+        let path = dir.join("test.cast");
+        let recorder = Recorder::create_at(&path, "test".to_string(), &test_header(capture_input)).unwrap();
+        recorder.output("$ ");
+        recorder.input("passwd\n");
+        recorder.resize(100, 40);
+        recorder.output("");
+        path
+    }
+
+    #[test]
+    fn header_keeps_asciicast_fields_and_extras() {
+        let value = serde_json::to_value(test_header(true)).unwrap();
+        assert_eq!(value, json!({
+            "version": 2,
+            "width": 80,
+            "height": 24,
+            "timestamp": 1_700_000_000,
+            "title": "root@10.0.0.5:22",
+            "env": {"TERM": "xterm-256color"},
+            "host": "10.0.0.5",
+            "port": 22,
+            "username": "root",
+            "session_id": "session-1",
+            "kind": "shell",
+            "capture_input": true,
+        }));
+
+        // asciinema 录制的文件没有附加字段
+        let header: CastHeader = serde_json::from_str(r#"{"version": 2, "width": 80, "height": 24, "timestamp": 0}"#).unwrap();
+        assert_eq!(header.kind, RecordingKind::Terminal);
+        assert!(!header.capture_input);
+        assert!(header.host.is_empty());
+    }
+
+    #[test]
+    fn events_are_written_as_json_lines() {
+        let dir = test_dir();
+        let path = record(&dir, true);
+
+        let content = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = content.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["session_id"], "session-1");
+        let events: Vec<_> = lines[1..].iter().map(|event| (event[1].clone(), event[2].clone())).collect();
+        assert_eq!(events, [
+            (json!("o"), json!("$ ")),
+            (json!("i"), json!("passwd\n")),
+            (json!("r"), json!("100x40")),
+        ]);
+        assert!(lines[1][0].as_f64().unwrap() <= lines[3][0].as_f64().unwrap());
+
+        let event: CastEvent = serde_json::from_str(r#"[1.25, "o", "ok\r\n"]"#).unwrap();
+        assert_eq!((event.0, event.1.as_str(), event.2.as_str()), (1.25, "o", "ok\r\n"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn input_is_recorded_only_when_captured() {
+        let dir = test_dir();
+        let path = record(&dir, false);
+
+        let (header, events) = read_cast(&path).unwrap();
+        assert!(!header.capture_input);
+        let codes: Vec<_> = events.iter().map(|event| event.1.as_str()).collect();
+        assert_eq!(codes, ["o", "r"]);
+        assert!(!fs::read_to_string(&path).unwrap().contains("passwd"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tolerates_truncated_last_line() {
+        let dir = test_dir();
+        let path = record(&dir, false);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "[9.5, \"o\", \"unfin").unwrap();
+
+        let (header, events) = read_cast(&path).unwrap();
+        assert_eq!(header.kind, RecordingKind::Shell);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[1].2, "100x40");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn export_refuses_to_overwrite_unless_asked() {
+        let dir = test_dir();
+        let source = record(&dir, false);
+        let destination = dir.join("export.cast");
+        fs::write(&destination, "existing").unwrap();
+
+        let result = copy_recording(&source, &destination, false);
+        assert!(matches!(result, Err(EmsError::InvalidInput(_))));
+        assert_eq!(fs::read_to_string(&destination).unwrap(), "existing");
+
+        copy_recording(&source, &destination, true).unwrap();
+        assert_eq!(fs::read(&destination).unwrap(), fs::read(&source).unwrap());
+
+        // 导出到录制文件本身会清空录制
+        assert!(copy_recording(&source, &source, true).is_err());
+        assert_eq!(fs::read(&destination).unwrap(), fs::read(&source).unwrap());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
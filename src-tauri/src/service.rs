@@ -1,10 +1,15 @@
-use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 use tauri::State;
 use serde::{Deserialize, Serialize};
 
 use crate::{AppState, ServiceConfig};
-use crate::ssh::{SshSession, execute_command};
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{SshSessions, run_blocking, run_command, run_command_with_input};
+use crate::command::{ShellCommand, validate_remote_path, validate_unit_name};
 use crate::logger::add_log_internal;
+use crate::privilege::Sudo;
+use crate::vault::Vault;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ServiceStatus {
@@ -18,371 +23,481 @@ pub struct ServiceStatus {
 pub async fn import_service(
     config: ServiceConfig,
     service_content: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let log_message = format!("导入服务 {}", config.name);
-    add_log_internal(&app_state, "INFO", &log_message);
+) -> EmsResult<String> {
+    let ssh_state = ssh_state.get(&session_id)?;
+    let sudo = Sudo::new(ssh_state.clone(), vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let name = validate_unit_name(&config.name)?;
+        let service_file = validate_remote_path(&config.service_file)?;
+        let log_message = format!("导入服务 {}", name);
+        add_log_internal(&app_state, "INFO", &log_message);
     
-    // 创建临时文件
-    let temp_file = format!("/tmp/{}.service", config.name);
-    let write_cmd = format!("cat > {} << 'EOL'\n{}\nEOL", temp_file, service_content);
-    execute_command(&ssh_state, &write_cmd)?;
+        // 创建临时文件，内容通过stdin写入，不经过shell解析
+        let temp_file = format!("/tmp/{}.service", name);
+        let write_cmd = ShellCommand::new("cat").raw(">").arg(&temp_file).to_string();
+        let write_output = run_command_with_input(&ssh_state, &write_cmd, Some(service_content.as_bytes()))?;
+        if !write_output.success() {
+            return Err(write_output.to_error());
+        }
     
-    // 复制到systemd目录
-    let copy_cmd = format!("sudo cp {} {}", temp_file, config.service_file);
-    execute_command(&ssh_state, &copy_cmd)?;
+        // 复制到systemd目录
+        let copy_cmd = ShellCommand::new("cp").arg(&temp_file).arg(service_file).to_string();
+        sudo.execute(&copy_cmd)?;
     
-    // 重新加载systemd
-    execute_command(&ssh_state, "sudo systemctl daemon-reload")?;
+        // 重新加载systemd
+        sudo.execute("systemctl daemon-reload")?;
     
-    // 如果需要，启用自启动
-    if config.enable_autostart {
-        let enable_cmd = format!("sudo systemctl enable {}", config.name);
-        execute_command(&ssh_state, &enable_cmd)?;
-        add_log_internal(&app_state, "INFO", &format!("已启用服务 {} 的自启动", config.name));
-    }
+        // 如果需要，启用自启动
+        if config.enable_autostart {
+            let enable_cmd = ShellCommand::new("systemctl").arg("enable").arg(name).to_string();
+            sudo.execute(&enable_cmd)?;
+            add_log_internal(&app_state, "INFO", &format!("已启用服务 {} 的自启动", config.name));
+        }
     
-    let success_message = format!("成功导入服务 {}", config.name);
-    add_log_internal(&app_state, "INFO", &success_message);
+        let success_message = format!("成功导入服务 {}", config.name);
+        add_log_internal(&app_state, "INFO", &success_message);
     
-    Ok(success_message)
+        Ok(success_message)
+    }).await
 }
 
 #[tauri::command]
 pub async fn enable_service(
     name: String,
     enable: bool,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let action = if enable { "启用" } else { "禁用" };
-    let log_message = format!("{} 服务 {} 的自启动", action, name);
-    add_log_internal(&app_state, "INFO", &log_message);
+) -> EmsResult<String> {
+    validate_unit_name(&name)?;
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let action = if enable { "启用" } else { "禁用" };
+        let log_message = format!("{} 服务 {} 的自启动", action, name);
+        add_log_internal(&app_state, "INFO", &log_message);
     
-    let cmd = if enable {
-        format!("sudo systemctl enable {}", name)
-    } else {
-        format!("sudo systemctl disable {}", name)
-    };
+        let cmd = ShellCommand::new("systemctl")
+            .arg(if enable { "enable" } else { "disable" })
+            .arg(&name)
+            .to_string();
     
-    execute_command(&ssh_state, &cmd)?;
+        sudo.execute(&cmd)?;
     
-    let success_message = format!("成功{} {} 服务的自启动", action, name);
-    add_log_internal(&app_state, "INFO", &success_message);
+        let success_message = format!("成功{} {} 服务的自启动", action, name);
+        add_log_internal(&app_state, "INFO", &success_message);
     
-    Ok(success_message)
+        Ok(success_message)
+    }).await
 }
 
 #[tauri::command]
 pub async fn start_service(
     name: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let log_message = format!("启动服务 {}", name);
-    add_log_internal(&app_state, "INFO", &log_message);
+) -> EmsResult<String> {
+    validate_unit_name(&name)?;
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("启动服务 {}", name);
+        add_log_internal(&app_state, "INFO", &log_message);
     
-    let cmd = format!("sudo systemctl start {}", name);
-    let start_result = execute_command(&ssh_state, &cmd);
-
-    // 等待一下让服务有时间启动
-    tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-
-    // 检查服务状态
-    let status_cmd = format!("sudo systemctl is-active {}", name);
-    let status = execute_command(&ssh_state, &status_cmd)?;
-
-    if status.trim() == "active" {
-        let success_message = format!("成功启动服务 {}", name);
-        add_log_internal(&app_state, "INFO", &success_message);
-        Ok(success_message)
-    } else {
-        // 获取详细的错误信息
-        let mut error_details = Vec::new();
+        let cmd = ShellCommand::new("systemctl").arg("start").arg(&name).to_string();
+        let start_result = sudo.run(&cmd);
+
+        // 等待一下让服务有时间启动
+        thread::sleep(Duration::from_millis(1000));
+
+        // 检查服务状态（is-active 仅在服务运行时返回0）
+        let status_cmd = ShellCommand::new("systemctl").arg("is-active").arg(&name).to_string();
+        let status_output = sudo.run(&status_cmd)?;
+        let status = &status_output.stdout;
+
+        if status_output.success() {
+            let success_message = format!("成功启动服务 {}", name);
+            add_log_internal(&app_state, "INFO", &success_message);
+            Ok(success_message)
+        } else {
+            // 获取详细的错误信息
+            let mut error_details = Vec::new();
+
+            // 检查启动命令是否成功
+            match start_result {
+                Ok(output) if !output.success() => {
+                    error_details.push(format!("启动命令执行失败: {}", output.error_message()));
+                }
+                Err(start_error) => error_details.push(format!("启动命令执行失败: {}", start_error)),
+                _ => {}
+            }
 
-        // 检查启动命令是否成功
-        if let Err(start_error) = start_result {
-            error_details.push(format!("启动命令执行失败: {}", start_error));
-        }
+            // 获取服务状态详情（服务未运行时 systemctl status 返回非0，但仍有输出）
+            let status_detail_cmd = ShellCommand::new("systemctl")
+                .arg("status")
+                .arg(&name)
+                .args(["--no-pager", "-l"])
+                .to_string();
+            if let Ok(status_detail) = sudo.run(&status_detail_cmd) {
+                error_details.push(format!("服务状态详情: {}", status_detail.stdout));
+            }
 
-        // 获取服务状态详情
-        let status_detail_cmd = format!("sudo systemctl status {} --no-pager -l", name);
-        if let Ok(status_detail) = execute_command(&ssh_state, &status_detail_cmd) {
-            error_details.push(format!("服务状态详情: {}", status_detail));
-        }
+            // 获取最近的日志
+            let journal_cmd = ShellCommand::new("journalctl")
+                .arg("-u")
+                .arg(&name)
+                .args(["--no-pager", "-n", "10"])
+                .to_string();
+            if let Ok(journal_output) = sudo.execute(&journal_cmd) {
+                error_details.push(format!("最近日志: {}", journal_output));
+            }
 
-        // 获取最近的日志
-        let journal_cmd = format!("sudo journalctl -u {} --no-pager -n 10", name);
-        if let Ok(journal_output) = execute_command(&ssh_state, &journal_cmd) {
-            error_details.push(format!("最近日志: {}", journal_output));
+            let error_message = format!(
+                "服务 {} 启动失败，当前状态: {}\n详细信息:\n{}",
+                name,
+                status.trim(),
+                error_details.join("\n")
+            );
+            add_log_internal(&app_state, "ERROR", &error_message);
+            Err(EmsError::CommandFailed {
+                exit_code: status_output.exit_code,
+                stderr: error_message,
+            })
         }
-
-        let error_message = format!(
-            "服务 {} 启动失败，当前状态: {}\n详细信息:\n{}",
-            name,
-            status.trim(),
-            error_details.join("\n")
-        );
-        add_log_internal(&app_state, "ERROR", &error_message);
-        Err(error_message)
-    }
+    }).await
 }
 
 #[tauri::command]
 pub async fn stop_service(
     name: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let log_message = format!("停止服务 {}", name);
-    add_log_internal(&app_state, "INFO", &log_message);
+) -> EmsResult<String> {
+    validate_unit_name(&name)?;
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("停止服务 {}", name);
+        add_log_internal(&app_state, "INFO", &log_message);
     
-    let cmd = format!("sudo systemctl stop {}", name);
-    execute_command(&ssh_state, &cmd)?;
+        let cmd = ShellCommand::new("systemctl").arg("stop").arg(&name).to_string();
+        sudo.execute(&cmd)?;
     
-    // 检查服务状态
-    let status_cmd = format!("sudo systemctl is-active {} || echo 'inactive'", name);
-    let status = execute_command(&ssh_state, &status_cmd)?;
+        // 检查服务状态（is-active 返回非0表示服务未运行）
+        let status_cmd = ShellCommand::new("systemctl").arg("is-active").arg(&name).to_string();
+        let status_output = sudo.run(&status_cmd)?;
+        let status = &status_output.stdout;
     
-    if status.trim() == "inactive" || status.trim() == "unknown" {
-        let success_message = format!("成功停止服务 {}", name);
-        add_log_internal(&app_state, "INFO", &success_message);
-        Ok(success_message)
-    } else {
-        let error_message = format!("服务 {} 停止失败，状态: {}", name, status.trim());
-        add_log_internal(&app_state, "ERROR", &error_message);
-        Err(error_message)
-    }
+        if !status_output.success() {
+            let success_message = format!("成功停止服务 {}", name);
+            add_log_internal(&app_state, "INFO", &success_message);
+            Ok(success_message)
+        } else {
+            let error_message = format!("服务 {} 停止失败，状态: {}", name, status.trim());
+            add_log_internal(&app_state, "ERROR", &error_message);
+            Err(EmsError::CommandFailed {
+                exit_code: status_output.exit_code,
+                stderr: error_message,
+            })
+        }
+    }).await
 }
 
 #[tauri::command]
 pub async fn get_service_status(
     name: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<ServiceStatus, String> {
-    let log_message = format!("获取服务 {} 的状态", name);
-    add_log_internal(&app_state, "INFO", &log_message);
+) -> EmsResult<ServiceStatus> {
+    validate_unit_name(&name)?;
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("获取服务 {} 的状态", name);
+        add_log_internal(&app_state, "INFO", &log_message);
     
-    // 检查服务是否启用
-    let enabled_cmd = format!("sudo systemctl is-enabled {} || echo 'disabled'", name);
-    let enabled_output = execute_command(&ssh_state, &enabled_cmd)?;
-    let enabled = enabled_output.trim() == "enabled";
+        // 检查服务是否启用
+        let enabled_cmd = ShellCommand::new("systemctl").arg("is-enabled").arg(&name).to_string();
+        let enabled_output = sudo.run(&enabled_cmd)?;
+        let enabled = enabled_output.success() && enabled_output.stdout.trim() == "enabled";
     
-    // 检查服务是否活动
-    let active_cmd = format!("sudo systemctl is-active {} || echo 'inactive'", name);
-    let active_output = execute_command(&ssh_state, &active_cmd)?;
-    let active = active_output.trim() == "active";
+        // 检查服务是否活动
+        let active_cmd = ShellCommand::new("systemctl").arg("is-active").arg(&name).to_string();
+        let active_output = sudo.run(&active_cmd)?;
+        let active = active_output.success();
     
-    // 获取详细状态描述
-    let status_description = if active {
-        "运行中".to_string()
-    } else {
-        // 尝试获取更详细的状态信息
-        let status_cmd = format!("sudo systemctl is-failed {} 2>/dev/null || echo 'stopped'", name);
-        let status_output = execute_command(&ssh_state, &status_cmd).unwrap_or_else(|_| "未知".to_string());
-        let status_trim = status_output.trim();
-
-        match status_trim {
-            "failed" => "启动失败".to_string(),
-            "stopped" => "已停止".to_string(),
-            "inactive" => "未激活".to_string(),
-            _ => "已停止".to_string(),
-        }
-    };
-
-    let status = ServiceStatus {
-        name: name.clone(),
-        status: status_description,
-        enabled,
-        active,
-    };
+        // 获取详细状态描述
+        let status_description = if active {
+            "运行中".to_string()
+        } else {
+            // is-failed 仅在服务处于失败状态时返回0
+            let status_cmd = ShellCommand::new("systemctl").arg("is-failed").arg(&name).to_string();
+            match sudo.run(&status_cmd) {
+                Ok(output) if output.success() => "启动失败".to_string(),
+                Ok(_) if active_output.stdout.trim() == "inactive" => "未激活".to_string(),
+                Ok(_) => "已停止".to_string(),
+                Err(_) => "未知".to_string(),
+            }
+        };
+
+        let status = ServiceStatus {
+            name: name.clone(),
+            status: status_description,
+            enabled,
+            active,
+        };
     
-    add_log_internal(&app_state, "INFO", &format!("成功获取服务 {} 的状态", name));
+        add_log_internal(&app_state, "INFO", &format!("成功获取服务 {} 的状态", name));
 
-    Ok(status)
+        Ok(status)
+    }).await
 }
 
 #[tauri::command]
 pub async fn search_services(
     query: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<Vec<String>, String> {
-    let log_message = format!("搜索服务: {}", query);
-    add_log_internal(&app_state, "INFO", &log_message);
-
-    // 搜索包含查询字符串的服务
-    let search_cmd = if query.trim().is_empty() {
-        // 如果查询为空，列出所有服务
-        "sudo systemctl list-unit-files --type=service | grep -E '\\.service' | awk '{print $1}' | sed 's/\\.service$//' | head -20".to_string()
-    } else {
-        // 模糊搜索服务名称
-        format!("sudo systemctl list-unit-files --type=service | grep -i '{}' | awk '{{print $1}}' | sed 's/\\.service$//' | head -10", query)
-    };
-
-    let output = execute_command(&ssh_state, &search_cmd)?;
-    let services: Vec<String> = output
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.trim().to_string())
-        .collect();
-
-    let success_message = format!("找到 {} 个匹配的服务", services.len());
-    add_log_internal(&app_state, "INFO", &success_message);
-
-    Ok(services)
+) -> EmsResult<Vec<String>> {
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("搜索服务: {}", query);
+        add_log_internal(&app_state, "INFO", &log_message);
+
+        // 搜索包含查询字符串的服务
+        let search_cmd = if query.trim().is_empty() {
+            // 如果查询为空，列出所有服务
+            "systemctl list-unit-files --type=service | grep -E '\\.service' | awk '{print $1}' | sed 's/\\.service$//' | head -20".to_string()
+        } else {
+            // 模糊搜索服务名称
+            // 按固定字符串匹配，查询内容不会被当作正则或shell语法
+            ShellCommand::new("systemctl")
+                .args(["list-unit-files", "--type=service"])
+                .pipe(ShellCommand::new("grep").args(["-i", "-F", "-e", query.trim()]))
+                .pipe(ShellCommand::new("awk").arg("{print $1}"))
+                .pipe(ShellCommand::new("sed").arg("s/\\.service$//"))
+                .pipe(ShellCommand::new("head").arg("-10"))
+                .to_string()
+        };
+
+        let output = sudo.execute(&search_cmd)?;
+        let services: Vec<String> = output
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.trim().to_string())
+            .collect();
+
+        let success_message = format!("找到 {} 个匹配的服务", services.len());
+        add_log_internal(&app_state, "INFO", &success_message);
+
+        Ok(services)
+    }).await
 }
 
 #[tauri::command]
 pub async fn diagnose_service(
     name: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let log_message = format!("诊断服务 {}", name);
-    add_log_internal(&app_state, "INFO", &log_message);
-
-    let mut diagnosis = Vec::new();
-
-    // 1. 检查服务文件是否存在
-    let service_file_cmd = format!("sudo systemctl cat {} 2>/dev/null || echo 'SERVICE_FILE_NOT_FOUND'", name);
-    match execute_command(&ssh_state, &service_file_cmd) {
-        Ok(output) => {
-            if output.trim() == "SERVICE_FILE_NOT_FOUND" {
-                diagnosis.push("❌ 服务文件不存在".to_string());
-            } else {
-                diagnosis.push("✅ 服务文件存在".to_string());
+) -> EmsResult<String> {
+    validate_unit_name(&name)?;
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("诊断服务 {}", name);
+        add_log_internal(&app_state, "INFO", &log_message);
+
+        let mut diagnosis = Vec::new();
+
+        // 1. 检查服务文件是否存在
+        let service_file_cmd = ShellCommand::new("systemctl").arg("cat").arg(&name).to_string();
+        match sudo.run(&service_file_cmd) {
+            Ok(output) => {
+                if output.success() {
+                    diagnosis.push("✅ 服务文件存在".to_string());
+                } else {
+                    diagnosis.push("❌ 服务文件不存在".to_string());
+                }
             }
+            Err(e) => diagnosis.push(format!("⚠️ 无法检查服务文件: {}", e)),
         }
-        Err(e) => diagnosis.push(format!("⚠️ 无法检查服务文件: {}", e)),
-    }
-
-    // 2. 检查服务状态
-    let status_cmd = format!("sudo systemctl is-active {}", name);
-    match execute_command(&ssh_state, &status_cmd) {
-        Ok(status) => {
-            diagnosis.push(format!("📊 当前状态: {}", status.trim()));
+
+        // 2. 检查服务状态
+        let status_cmd = ShellCommand::new("systemctl").arg("is-active").arg(&name).to_string();
+        match sudo.run(&status_cmd) {
+            Ok(status) => {
+                diagnosis.push(format!("📊 当前状态: {}", status.stdout.trim()));
+            }
+            Err(e) => diagnosis.push(format!("⚠️ 无法获取状态: {}", e)),
         }
-        Err(e) => diagnosis.push(format!("⚠️ 无法获取状态: {}", e)),
-    }
-
-    // 3. 检查是否启用
-    let enabled_cmd = format!("sudo systemctl is-enabled {}", name);
-    match execute_command(&ssh_state, &enabled_cmd) {
-        Ok(enabled) => {
-            diagnosis.push(format!("🔧 启用状态: {}", enabled.trim()));
+
+        // 3. 检查是否启用
+        let enabled_cmd = ShellCommand::new("systemctl").arg("is-enabled").arg(&name).to_string();
+        match sudo.run(&enabled_cmd) {
+            Ok(enabled) => {
+                diagnosis.push(format!("🔧 启用状态: {}", enabled.stdout.trim()));
+            }
+            Err(e) => diagnosis.push(format!("⚠️ 无法获取启用状态: {}", e)),
         }
-        Err(e) => diagnosis.push(format!("⚠️ 无法获取启用状态: {}", e)),
-    }
-
-    // 4. 获取详细状态
-    let detail_cmd = format!("sudo systemctl status {} --no-pager -l", name);
-    match execute_command(&ssh_state, &detail_cmd) {
-        Ok(detail) => {
-            diagnosis.push(format!("📋 详细状态:\n{}", detail));
+
+        // 4. 获取详细状态
+        // 服务未运行时 systemctl status 返回非0，但输出仍然有效
+        let detail_cmd = ShellCommand::new("systemctl")
+            .arg("status")
+            .arg(&name)
+            .args(["--no-pager", "-l"])
+            .to_string();
+        match sudo.run(&detail_cmd) {
+            Ok(detail) if !detail.stdout.trim().is_empty() => {
+                diagnosis.push(format!("📋 详细状态:\n{}", detail.stdout));
+            }
+            Ok(detail) => diagnosis.push(format!("⚠️ 无法获取详细状态: {}", detail.error_message())),
+            Err(e) => diagnosis.push(format!("⚠️ 无法获取详细状态: {}", e)),
         }
-        Err(e) => diagnosis.push(format!("⚠️ 无法获取详细状态: {}", e)),
-    }
-
-    // 5. 获取最近日志
-    let log_cmd = format!("sudo journalctl -u {} --no-pager -n 20", name);
-    match execute_command(&ssh_state, &log_cmd) {
-        Ok(logs) => {
-            diagnosis.push(format!("📝 最近日志:\n{}", logs));
+
+        // 5. 获取最近日志
+        let log_cmd = ShellCommand::new("journalctl")
+            .arg("-u")
+            .arg(&name)
+            .args(["--no-pager", "-n", "20"])
+            .to_string();
+        match sudo.execute(&log_cmd) {
+            Ok(logs) => {
+                diagnosis.push(format!("📝 最近日志:\n{}", logs));
+            }
+            Err(e) => diagnosis.push(format!("⚠️ 无法获取日志: {}", e)),
         }
-        Err(e) => diagnosis.push(format!("⚠️ 无法获取日志: {}", e)),
-    }
 
-    let result = diagnosis.join("\n\n");
-    add_log_internal(&app_state, "INFO", &format!("完成服务 {} 的诊断", name));
+        let result = diagnosis.join("\n\n");
+        add_log_internal(&app_state, "INFO", &format!("完成服务 {} 的诊断", name));
 
-    Ok(result)
+        Ok(result)
+    }).await
 }
 
 #[tauri::command]
 pub async fn find_service_executable(
     service_name: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let log_message = format!("查找服务 {} 的可执行文件", service_name);
-    add_log_internal(&app_state, "INFO", &log_message);
-
-    let mut search_results = Vec::new();
-
-    // 1. 查看服务配置文件内容
-    let service_config_cmd = format!("sudo systemctl cat {}", service_name);
-    match execute_command(&ssh_state, &service_config_cmd) {
-        Ok(config) => {
-            search_results.push(format!("📋 当前服务配置:\n{}", config));
+) -> EmsResult<String> {
+    validate_unit_name(&service_name)?;
+    let ssh_state = ssh_state.get(&session_id)?;
+    let sudo = Sudo::new(ssh_state.clone(), vault.inner().clone());
+    let app_state = app_state.inner().clone();
+
+    run_blocking(move || {
+        let log_message = format!("查找服务 {} 的可执行文件", service_name);
+        add_log_internal(&app_state, "INFO", &log_message);
+
+        let mut search_results = Vec::new();
+
+        // 1. 查看服务配置文件内容
+        let service_config_cmd = ShellCommand::new("systemctl").arg("cat").arg(&service_name).to_string();
+        match sudo.execute(&service_config_cmd) {
+            Ok(config) => {
+                search_results.push(format!("📋 当前服务配置:\n{}", config));
+            }
+            Err(e) => search_results.push(format!("⚠️ 无法读取服务配置: {}", e)),
         }
-        Err(e) => search_results.push(format!("⚠️ 无法读取服务配置: {}", e)),
-    }
-
-    // 2. 搜索可能的可执行文件位置
-    let search_patterns = vec![
-        format!("find /usr/local/bin -name '*{}*' 2>/dev/null", service_name),
-        format!("find /usr/bin -name '*{}*' 2>/dev/null", service_name),
-        format!("find /opt -name '*{}*' 2>/dev/null", service_name),
-        format!("find /home -name '*{}*' 2>/dev/null", service_name),
-        "find /ems -type f 2>/dev/null".to_string(),
-    ];
-
-    for pattern in search_patterns {
-        match execute_command(&ssh_state, &pattern) {
-            Ok(output) => {
-                if !output.trim().is_empty() {
-                    search_results.push(format!("🔍 找到文件:\n{}", output));
+
+        // 2. 搜索可能的可执行文件位置
+        let name_pattern = format!("*{}*", service_name);
+        let mut search_patterns: Vec<String> = ["/usr/local/bin", "/usr/bin", "/opt", "/home"]
+            .iter()
+            .map(|dir| {
+                ShellCommand::new("find").arg(dir).arg("-name").arg(&name_pattern).raw("2>/dev/null").to_string()
+            })
+            .collect();
+        search_patterns.push("find /ems -type f 2>/dev/null".to_string());
+
+        for pattern in search_patterns {
+            // 部分目录不存在时find返回非0，但已找到的结果仍然有效
+            if let Ok(output) = run_command(&ssh_state, &pattern) {
+                if !output.stdout.trim().is_empty() {
+                    search_results.push(format!("🔍 找到文件:\n{}", output.stdout));
                 }
             }
-            Err(_) => {} // 忽略搜索错误
         }
-    }
 
-    // 3. 检查 /ems 目录状态
-    let ems_dir_cmd = "ls -la /ems/ 2>/dev/null || echo 'Directory /ems does not exist'";
-    match execute_command(&ssh_state, ems_dir_cmd) {
-        Ok(output) => {
-            search_results.push(format!("📁 /ems 目录内容:\n{}", output));
+        // 3. 检查 /ems 目录状态
+        let ems_dir_cmd = "ls -la /ems/";
+        match run_command(&ssh_state, ems_dir_cmd) {
+            Ok(output) if output.success() => {
+                search_results.push(format!("📁 /ems 目录内容:\n{}", output.stdout));
+            }
+            Ok(_) => search_results.push("📁 /ems 目录不存在".to_string()),
+            Err(e) => search_results.push(format!("⚠️ 无法检查 /ems 目录: {}", e)),
         }
-        Err(e) => search_results.push(format!("⚠️ 无法检查 /ems 目录: {}", e)),
-    }
 
-    let result = search_results.join("\n\n");
-    add_log_internal(&app_state, "INFO", &format!("完成服务 {} 可执行文件查找", service_name));
+        let result = search_results.join("\n\n");
+        add_log_internal(&app_state, "INFO", &format!("完成服务 {} 可执行文件查找", service_name));
 
-    Ok(result)
+        Ok(result)
+    }).await
 }
 
 #[tauri::command]
 pub async fn force_stop_service(
     name: String,
-    ssh_state: State<'_, Arc<Mutex<SshSession>>>,
+    session_id: String,
+    ssh_state: State<'_, SshSessions>,
+    vault: State<'_, Vault>,
     app_state: State<'_, AppState>,
-) -> Result<String, String> {
-    let log_message = format!("强制停止服务 {}", name);
-    add_log_internal(&app_state, "INFO", &log_message);
+) -> EmsResult<String> {
+    validate_unit_name(&name)?;
+    let sudo = Sudo::new(ssh_state.get(&session_id)?, vault.inner().clone());
+    let app_state = app_state.inner().clone();
 
-    // 1. 停止服务
-    let stop_cmd = format!("sudo systemctl stop {}", name);
-    let _ = execute_command(&ssh_state, &stop_cmd); // 忽略错误，继续执行
+    run_blocking(move || {
+        let log_message = format!("强制停止服务 {}", name);
+        add_log_internal(&app_state, "INFO", &log_message);
 
-    // 2. 禁用服务自动重启
-    let disable_cmd = format!("sudo systemctl disable {}", name);
-    let _ = execute_command(&ssh_state, &disable_cmd); // 忽略错误，继续执行
+        // 1. 停止服务
+        let stop_cmd = ShellCommand::new("systemctl").arg("stop").arg(&name).to_string();
+        let _ = sudo.execute(&stop_cmd); // 忽略错误，继续执行
 
-    // 3. 重置失败状态
-    let reset_cmd = format!("sudo systemctl reset-failed {}", name);
-    let _ = execute_command(&ssh_state, &reset_cmd); // 忽略错误，继续执行
+        // 2. 禁用服务自动重启
+        let disable_cmd = ShellCommand::new("systemctl").arg("disable").arg(&name).to_string();
+        let _ = sudo.execute(&disable_cmd); // 忽略错误，继续执行
 
-    // 4. 检查最终状态
-    let status_cmd = format!("sudo systemctl is-active {} || echo 'inactive'", name);
-    let final_status = execute_command(&ssh_state, &status_cmd)?;
+        // 3. 重置失败状态
+        let reset_cmd = ShellCommand::new("systemctl").arg("reset-failed").arg(&name).to_string();
+        let _ = sudo.execute(&reset_cmd); // 忽略错误，继续执行
 
-    let success_message = format!("服务 {} 已强制停止，当前状态: {}", name, final_status.trim());
-    add_log_internal(&app_state, "INFO", &success_message);
+        // 4. 检查最终状态
+        let status_cmd = ShellCommand::new("systemctl").arg("is-active").arg(&name).to_string();
+        let final_status = sudo.run(&status_cmd)?;
 
-    Ok(success_message)
+        let success_message = format!("服务 {} 已强制停止，当前状态: {}", name, final_status.stdout.trim());
+        add_log_internal(&app_state, "INFO", &success_message);
+
+        Ok(success_message)
+    }).await
 }
\ No newline at end of file
@@ -0,0 +1,159 @@
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use ssh2::{File, FileStat, OpenFlags, OpenType, Sftp};
+
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{SharedSshSession, SshSession};
+
+// 只在每次SFTP操作期间持有会话锁的句柄
+// 传输大文件时锁在块之间释放，同一连接上的Shell、命令执行、端口转发和心跳检测不会被阻塞
+pub struct Locked<T, S = SshSession> {
+    session: Arc<Mutex<S>>,
+    // 只在 drop 时取出，关闭句柄同样需要持有会话锁
+    inner: Option<T>,
+}
+
+pub type RemoteFs = Locked<Sftp>;
+pub type RemoteFile = Locked<File>;
+
+impl<T, S> Locked<T, S> {
+    pub fn new(session: Arc<Mutex<S>>, inner: T) -> Self {
+        Locked { session, inner: Some(inner) }
+    }
+
+    // 持有会话锁执行一次操作
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let _guard = lock(&self.session);
+        f(self.inner.as_ref().expect("SFTP句柄已关闭"))
+    }
+}
+
+// 其他线程在持有会话锁时panic不影响SFTP句柄本身，继续使用
+fn lock<S>(session: &Mutex<S>) -> MutexGuard<'_, S> {
+    session.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl<T, S> Drop for Locked<T, S> {
+    fn drop(&mut self) {
+        let _guard = lock(&self.session);
+        self.inner.take();
+    }
+}
+
+impl<T: Read, S> Read for Locked<T, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let _guard = lock(&self.session);
+        self.inner.as_mut().expect("SFTP句柄已关闭").read(buf)
+    }
+}
+
+impl<T: Write, S> Write for Locked<T, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let _guard = lock(&self.session);
+        self.inner.as_mut().expect("SFTP句柄已关闭").write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _guard = lock(&self.session);
+        self.inner.as_mut().expect("SFTP句柄已关闭").flush()
+    }
+}
+
+impl<T: Seek, S> Seek for Locked<T, S> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let _guard = lock(&self.session);
+        self.inner.as_mut().expect("SFTP句柄已关闭").seek(pos)
+    }
+}
+
+// 替换远程文件时用到的SFTP操作，测试中用内存实现代替
+pub trait SftpOps {
+    fn stat(&self, path: &Path) -> Result<FileStat, ssh2::Error>;
+    fn setstat(&self, path: &Path, stat: FileStat) -> Result<(), ssh2::Error>;
+    fn rename(&self, src: &Path, dst: &Path) -> Result<(), ssh2::Error>;
+    fn unlink(&self, path: &Path) -> Result<(), ssh2::Error>;
+}
+
+impl SftpOps for Sftp {
+    fn stat(&self, path: &Path) -> Result<FileStat, ssh2::Error> {
+        Sftp::stat(self, path)
+    }
+
+    fn setstat(&self, path: &Path, stat: FileStat) -> Result<(), ssh2::Error> {
+        Sftp::setstat(self, path, stat)
+    }
+
+    fn rename(&self, src: &Path, dst: &Path) -> Result<(), ssh2::Error> {
+        Sftp::rename(self, src, dst, None)
+    }
+
+    fn unlink(&self, path: &Path) -> Result<(), ssh2::Error> {
+        Sftp::unlink(self, path)
+    }
+}
+
+// 在会话上打开SFTP子系统，之后的操作不需要调用方持有会话锁
+pub fn open_sftp(ssh_session: &SharedSshSession) -> EmsResult<RemoteFs> {
+    let sftp = {
+        let ssh_guard = ssh_session.lock()?;
+        let session = ssh_guard.session.as_ref().ok_or(EmsError::NotConnected)?;
+        session.sftp()
+            .map_err(|e| EmsError::SftpError(format!("无法创建SFTP会话: {}", e)))?
+    };
+    Ok(Locked::new(ssh_session.clone(), sftp))
+}
+
+impl RemoteFs {
+    pub fn open(&self, path: &Path) -> Result<RemoteFile, ssh2::Error> {
+        self.open_mode(path, OpenFlags::READ, 0o644, OpenType::File)
+    }
+
+    pub fn create(&self, path: &Path) -> Result<RemoteFile, ssh2::Error> {
+        self.open_mode(path, OpenFlags::WRITE | OpenFlags::TRUNCATE, 0o644, OpenType::File)
+    }
+
+    pub fn open_mode(&self, path: &Path, flags: OpenFlags, mode: i32, open_type: OpenType) -> Result<RemoteFile, ssh2::Error> {
+        let file = self.with(|sftp| sftp.open_mode(path, flags, mode, open_type))?;
+        Ok(Locked::new(self.session.clone(), file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 记录每次写入时会话锁是否被持有
+    struct Probe {
+        session: Arc<Mutex<()>>,
+        locked_during_write: Vec<bool>,
+    }
+
+    impl Write for Probe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.locked_during_write.push(self.session.try_lock().is_err());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn holds_session_lock_only_during_each_chunk() {
+        let session = Arc::new(Mutex::new(()));
+        let probe = Probe { session: session.clone(), locked_during_write: Vec::new() };
+        let mut file = Locked::new(session.clone(), probe);
+
+        for chunk in vec![0u8; 4096].chunks(1024) {
+            file.write_all(chunk).unwrap();
+            // 两块之间其他线程（例如Shell读取线程）可以获取会话锁
+            let other = session.clone();
+            assert!(std::thread::spawn(move || other.try_lock().is_ok()).join().unwrap());
+        }
+
+        let writes = file.with(|probe| probe.locked_during_write.clone());
+        assert_eq!(writes, vec![true; 4]);
+    }
+}
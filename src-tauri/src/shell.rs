@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use ssh2::Channel;
+use tauri::{AppHandle, Manager, State};
+use serde::Serialize;
+
+use crate::AppState;
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::{SharedSshSession, SshSessions, run_blocking, with_nonblocking};
+use crate::logger::add_log_internal;
+use crate::recording::Recordings;
+
+// 没有输出时读取线程的轮询间隔
+const SHELL_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ShellOutputEvent {
+    shell_id: String,
+    data: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ShellClosedEvent {
+    shell_id: String,
+    exit_status: Option<i32>,
+}
+
+// 交互式PTY Shell
+pub struct ShellChannel {
+    session_id: String,
+    ssh_session: SharedSshSession,
+    channel: Mutex<Channel>,
+    closed: Mutex<bool>,
+    // 终端尺寸（列, 行），录制时写入文件头
+    size: Mutex<(u32, u32)>,
+}
+
+impl ShellChannel {
+    fn is_closed(&self) -> bool {
+        self.closed.lock().map(|closed| *closed).unwrap_or(true)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.size.lock().map(|size| *size).unwrap_or((80, 24))
+    }
+
+    fn mark_closed(&self) {
+        if let Ok(mut closed) = self.closed.lock() {
+            *closed = true;
+        }
+    }
+}
+
+// 所有打开的Shell，按Shell ID索引
+pub struct ShellSessions {
+    shells: Mutex<HashMap<String, Arc<ShellChannel>>>,
+}
+
+impl ShellSessions {
+    pub fn new() -> Self {
+        ShellSessions {
+            shells: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, shell_id: &str) -> EmsResult<Arc<ShellChannel>> {
+        let shells = self.shells.lock()?;
+        shells
+            .get(shell_id)
+            .cloned()
+            .ok_or_else(|| EmsError::NotFound(format!("Shell不存在: {}", shell_id)))
+    }
+
+    fn insert(&self, shell_id: String, shell: Arc<ShellChannel>) -> EmsResult<()> {
+        let mut shells = self.shells.lock()?;
+        shells.insert(shell_id, shell);
+        Ok(())
+    }
+
+    fn remove(&self, shell_id: &str) -> Option<Arc<ShellChannel>> {
+        self.shells.lock().ok().and_then(|mut shells| shells.remove(shell_id))
+    }
+
+    // 结束某个会话上的所有Shell（连接断开时调用），读取线程随后退出并发送 ssh-shell-closed 事件
+    // 通道所在的连接已失效，不再关闭通道
+    pub fn close_session_shells(&self, session_id: &str) -> usize {
+        let Ok(mut shells) = self.shells.lock() else {
+            return 0;
+        };
+        let shell_ids: Vec<String> = shells
+            .iter()
+            .filter(|(_, shell)| shell.session_id == session_id)
+            .map(|(shell_id, _)| shell_id.clone())
+            .collect();
+
+        for shell_id in &shell_ids {
+            if let Some(shell) = shells.remove(shell_id) {
+                shell.mark_closed();
+            }
+        }
+        shell_ids.len()
+    }
+}
+
+// 初始化Shell状态
+pub fn init_shell_state() -> ShellSessions {
+    ShellSessions::new()
+}
+
+// 打开交互式Shell，输出通过 ssh-shell-output 事件推送到前端
+#[tauri::command]
+pub async fn open_shell(
+    session_id: String,
+    cols: u32,
+    rows: u32,
+    app_handle: AppHandle,
+    ssh_state: State<'_, SshSessions>,
+    shell_state: State<'_, ShellSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<String> {
+    let ssh_session = ssh_state.get(&session_id)?;
+
+    let channel_session = ssh_session.clone();
+    let channel = run_blocking(move || {
+        let ssh_guard = channel_session.lock()?;
+        let session = ssh_guard.session.as_ref().ok_or(EmsError::NotConnected)?;
+
+        let mut channel = session.channel_session()
+            .map_err(|e| EmsError::Ssh(format!("无法创建会话通道: {}", e)))?;
+        channel.request_pty("xterm-256color", None, Some((cols, rows, 0, 0)))
+            .map_err(|e| EmsError::Ssh(format!("请求PTY失败: {}", e)))?;
+        channel.shell()
+            .map_err(|e| EmsError::Ssh(format!("启动Shell失败: {}", e)))?;
+        Ok(channel)
+    }).await?;
+
+    let shell_id = uuid::Uuid::new_v4().to_string();
+    let shell = Arc::new(ShellChannel {
+        session_id: session_id.clone(),
+        ssh_session,
+        channel: Mutex::new(channel),
+        closed: Mutex::new(false),
+        size: Mutex::new((cols, rows)),
+    });
+    shell_state.insert(shell_id.clone(), shell.clone())?;
+
+    let reader_shell_id = shell_id.clone();
+    thread::spawn(move || pump_shell_output(reader_shell_id, shell, app_handle));
+
+    let log_message = format!("打开交互式Shell: {} (会话: {}, {}x{})", shell_id, session_id, cols, rows);
+    add_log_internal(&app_state, "INFO", &log_message);
+    println!("✅ [SHELL] {}", log_message);
+
+    Ok(shell_id)
+}
+
+// 向Shell写入输入（按键、粘贴的文本等）
+#[tauri::command]
+pub async fn write_shell(
+    shell_id: String,
+    data: String,
+    shell_state: State<'_, ShellSessions>,
+    recordings: State<'_, Recordings>,
+) -> EmsResult<()> {
+    let shell = shell_state.get(&shell_id)?;
+    if shell.is_closed() {
+        return Err(EmsError::InvalidInput("Shell已关闭".to_string()));
+    }
+    if let Some(recorder) = recordings.get(&shell_id) {
+        recorder.input(&data);
+    }
+
+    // 远端窗口已满时写入会阻塞，放到后台线程执行
+    run_blocking(move || {
+        let mut channel = shell.channel.lock()?;
+        let _ssh_guard = shell.ssh_session.lock()?;
+        channel.write_all(data.as_bytes())
+            .map_err(|e| EmsError::Ssh(format!("写入Shell失败: {}", e)))?;
+        channel.flush()
+            .map_err(|e| EmsError::Ssh(format!("写入Shell失败: {}", e)))?;
+        Ok(())
+    }).await
+}
+
+// 调整Shell的终端尺寸
+#[tauri::command]
+pub async fn resize_shell(
+    shell_id: String,
+    cols: u32,
+    rows: u32,
+    shell_state: State<'_, ShellSessions>,
+    recordings: State<'_, Recordings>,
+) -> EmsResult<()> {
+    let shell = shell_state.get(&shell_id)?;
+
+    let resized_shell = shell.clone();
+    run_blocking(move || {
+        let mut channel = resized_shell.channel.lock()?;
+        let _ssh_guard = resized_shell.ssh_session.lock()?;
+        channel.request_pty_size(cols, rows, None, None)
+            .map_err(|e| EmsError::Ssh(format!("调整终端尺寸失败: {}", e)))?;
+        Ok(())
+    }).await?;
+
+    *shell.size.lock()? = (cols, rows);
+    if let Some(recorder) = recordings.get(&shell_id) {
+        recorder.resize(cols, rows);
+    }
+    Ok(())
+}
+
+// 关闭Shell
+#[tauri::command]
+pub async fn close_shell(
+    shell_id: String,
+    shell_state: State<'_, ShellSessions>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    let shell = shell_state.remove(&shell_id)
+        .ok_or_else(|| EmsError::NotFound(format!("Shell不存在: {}", shell_id)))?;
+    shell.mark_closed();
+
+    run_blocking(move || {
+        if let (Ok(mut channel), Ok(_ssh_guard)) = (shell.channel.lock(), shell.ssh_session.lock()) {
+            if let Err(e) = channel.close() {
+                log::warn!("关闭Shell通道时出错: {}", e);
+            }
+        }
+        Ok(())
+    }).await?;
+
+    add_log_internal(&app_state, "INFO", &format!("关闭交互式Shell: {}", shell_id));
+    println!("✅ [SHELL] 关闭Shell: {}", shell_id);
+
+    Ok(())
+}
+
+// 读取线程：持续读取Shell输出并推送到前端，直到通道关闭
+fn pump_shell_output(shell_id: String, shell: Arc<ShellChannel>, app_handle: AppHandle) {
+    let mut buffer = [0u8; 8192];
+    let mut pending = Vec::new();
+
+    while !shell.is_closed() {
+        let read_result = {
+            let mut channel = match shell.channel.lock() {
+                Ok(channel) => channel,
+                Err(_) => break,
+            };
+            match with_nonblocking(&shell.ssh_session, || channel.read(&mut buffer)) {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("⚠️ [SHELL] {}", e);
+                    break;
+                }
+            }
+        };
+
+        match read_result {
+            Ok(0) => break,
+            Ok(n) => {
+                pending.extend_from_slice(&buffer[..n]);
+                let data = take_utf8(&mut pending);
+                if !data.is_empty() {
+                    if let Some(recorder) = app_handle.try_state::<Recordings>().and_then(|recordings| recordings.get(&shell_id)) {
+                        recorder.output(&data);
+                    }
+                    let _ = app_handle.emit_all("ssh-shell-output", ShellOutputEvent {
+                        shell_id: shell_id.clone(),
+                        data,
+                    });
+                }
+            }
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(SHELL_POLL_INTERVAL),
+            Err(e) => {
+                println!("❌ [SHELL] 读取Shell输出失败: {}", e);
+                break;
+            }
+        }
+    }
+
+    shell.mark_closed();
+    let exit_status = shell.channel.lock().ok().and_then(|channel| channel.exit_status().ok());
+    if let Some(shell_state) = app_handle.try_state::<ShellSessions>() {
+        shell_state.remove(&shell_id);
+    }
+    if let Some(recordings) = app_handle.try_state::<Recordings>() {
+        recordings.stop(&shell_id);
+    }
+
+    println!("🔧 [SHELL] Shell已结束: {}", shell_id);
+    let _ = app_handle.emit_all("ssh-shell-closed", ShellClosedEvent {
+        shell_id,
+        exit_status,
+    });
+}
+
+// 取出缓冲区中完整的UTF-8文本，被截断的多字节字符留到下一次
+pub fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid_len = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => {
+            // 非法的UTF-8序列，按有损方式转换后全部输出
+            let data = String::from_utf8_lossy(pending).into_owned();
+            pending.clear();
+            return data;
+        }
+    };
+
+    let rest = pending.split_off(valid_len);
+    String::from_utf8(std::mem::replace(pending, rest)).unwrap_or_default()
+}
@@ -0,0 +1,170 @@
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+// SOCKS5 协议（RFC 1928）的服务端握手，只支持无认证的 CONNECT 命令
+const SOCKS_VERSION: u8 = 0x05;
+const METHOD_NO_AUTH: u8 = 0x00;
+const METHOD_NO_ACCEPTABLE: u8 = 0xFF;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+// 回复给客户端的状态码
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SocksReply {
+    Succeeded = 0x00,
+    GeneralFailure = 0x01,
+    HostUnreachable = 0x04,
+    CommandNotSupported = 0x07,
+    AddressTypeNotSupported = 0x08,
+}
+
+// 完成协商并读取客户端请求的目标地址
+// 不支持的请求会先回复客户端再返回错误
+pub fn handshake<S: Read + Write>(stream: &mut S) -> Result<(String, u16)> {
+    // 方法协商: VER NMETHODS METHODS...
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header)?;
+    check_version(header[0])?;
+
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods)?;
+    if !methods.contains(&METHOD_NO_AUTH) {
+        stream.write_all(&[SOCKS_VERSION, METHOD_NO_ACCEPTABLE])?;
+        return Err(Error::new(ErrorKind::InvalidData, "客户端不支持无认证方式"));
+    }
+    stream.write_all(&[SOCKS_VERSION, METHOD_NO_AUTH])?;
+
+    // 连接请求: VER CMD RSV ATYP DST.ADDR DST.PORT
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request)?;
+    check_version(request[0])?;
+
+    let host = match request[3] {
+        ATYP_IPV4 => {
+            let mut octets = [0u8; 4];
+            stream.read_exact(&mut octets)?;
+            Ipv4Addr::from(octets).to_string()
+        }
+        ATYP_DOMAIN => {
+            let mut length = [0u8; 1];
+            stream.read_exact(&mut length)?;
+            let mut domain = vec![0u8; length[0] as usize];
+            stream.read_exact(&mut domain)?;
+            String::from_utf8(domain)
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "目标域名不是有效的UTF-8"))?
+        }
+        ATYP_IPV6 => {
+            let mut octets = [0u8; 16];
+            stream.read_exact(&mut octets)?;
+            Ipv6Addr::from(octets).to_string()
+        }
+        atyp => {
+            send_reply(stream, SocksReply::AddressTypeNotSupported)?;
+            return Err(Error::new(ErrorKind::InvalidData, format!("不支持的地址类型: {}", atyp)));
+        }
+    };
+
+    let mut port = [0u8; 2];
+    stream.read_exact(&mut port)?;
+    let port = u16::from_be_bytes(port);
+
+    if request[1] != CMD_CONNECT {
+        send_reply(stream, SocksReply::CommandNotSupported)?;
+        return Err(Error::new(ErrorKind::InvalidData, format!("不支持的命令: {}", request[1])));
+    }
+
+    Ok((host, port))
+}
+
+// 回复连接结果，绑定地址固定为 0.0.0.0:0（通道没有本地地址）
+pub fn send_reply<W: Write>(stream: &mut W, reply: SocksReply) -> Result<()> {
+    stream.write_all(&[SOCKS_VERSION, reply as u8, 0x00, ATYP_IPV4, 0, 0, 0, 0, 0, 0])?;
+    stream.flush()
+}
+
+fn check_version(version: u8) -> Result<()> {
+    if version != SOCKS_VERSION {
+        return Err(Error::new(ErrorKind::InvalidData, format!("不支持的SOCKS版本: {}", version)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // 内存中的双向流：读取预置的客户端数据，记录服务端写出的数据
+    struct MockStream {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl MockStream {
+        fn new(input: &[u8]) -> Self {
+            MockStream { input: Cursor::new(input.to_vec()), output: Vec::new() }
+        }
+    }
+
+    impl Read for MockStream {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for MockStream {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn parses_domain_connect() {
+        let mut stream = MockStream::new(&[
+            5, 1, 0,
+            5, 1, 0, 3, 11, b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c', b'o', b'm', 0, 80,
+        ]);
+        let target = handshake(&mut stream).unwrap();
+        assert_eq!(target, ("example.com".to_string(), 80));
+        assert_eq!(stream.output, vec![5, 0]);
+    }
+
+    #[test]
+    fn parses_ipv4_and_ipv6_connect() {
+        let mut stream = MockStream::new(&[5, 2, 2, 0, 5, 1, 0, 1, 192, 168, 1, 10, 0x1F, 0x90]);
+        assert_eq!(handshake(&mut stream).unwrap(), ("192.168.1.10".to_string(), 8080));
+
+        let mut request = vec![5, 1, 0, 5, 1, 0, 4];
+        request.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        request.extend_from_slice(&[0, 22]);
+        let mut stream = MockStream::new(&request);
+        assert_eq!(handshake(&mut stream).unwrap(), ("::1".to_string(), 22));
+    }
+
+    #[test]
+    fn rejects_clients_requiring_auth() {
+        let mut stream = MockStream::new(&[5, 1, 2]);
+        assert!(handshake(&mut stream).is_err());
+        assert_eq!(stream.output, vec![5, METHOD_NO_ACCEPTABLE]);
+    }
+
+    #[test]
+    fn rejects_bind_command() {
+        let mut stream = MockStream::new(&[5, 1, 0, 5, 2, 0, 1, 127, 0, 0, 1, 0, 80]);
+        assert!(handshake(&mut stream).is_err());
+        assert_eq!(stream.output[2..4], [5, SocksReply::CommandNotSupported as u8]);
+    }
+
+    #[test]
+    fn rejects_socks4() {
+        let mut stream = MockStream::new(&[4, 1, 0, 80, 127, 0, 0, 1, 0]);
+        assert!(handshake(&mut stream).is_err());
+        assert!(stream.output.is_empty());
+    }
+}
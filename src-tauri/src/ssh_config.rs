@@ -0,0 +1,608 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+
+use crate::{SshAuthMethod, SshConnectionConfig};
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::run_blocking;
+
+// 默认SSH端口
+const DEFAULT_SSH_PORT: u16 = 22;
+// Include 的最大嵌套深度，与OpenSSH一致
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// 配置文件中的一个 Host 块
+struct HostBlock {
+    // 为空表示不会匹配任何主机（例如不支持的 Match 块）
+    patterns: Vec<String>,
+    // 关键字统一为小写
+    options: Vec<(String, Vec<String>)>,
+}
+
+// 解析后的OpenSSH客户端配置（~/.ssh/config）
+pub struct SshConfig {
+    blocks: Vec<HostBlock>,
+}
+
+// 某个主机别名解析后的配置
+#[derive(Debug, Default)]
+struct ResolvedHost {
+    host_name: Option<String>,
+    user: Option<String>,
+    port: Option<u16>,
+    identity_files: Vec<String>,
+    proxy_jump: Option<String>,
+}
+
+// 返回给前端的主机条目
+#[derive(Debug, Serialize)]
+pub struct SshConfigHost {
+    alias: String,
+    host: String,
+    port: u16,
+    username: Option<String>,
+    identity_files: Vec<String>,
+    proxy_jump: Option<String>,
+}
+
+impl SshConfig {
+    // 加载当前用户的 ~/.ssh/config，文件不存在时返回 None
+    pub fn load_user_config() -> EmsResult<Option<Self>> {
+        let Some(ssh_dir) = user_ssh_dir() else {
+            return Ok(None);
+        };
+        let path = ssh_dir.join("config");
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut blocks = Vec::new();
+        parse_file(&path, &ssh_dir, vec!["*".to_string()], 0, &mut blocks)?;
+        Ok(Some(SshConfig { blocks }))
+    }
+
+    // 配置中明确写出的主机别名（不含通配符和否定模式）
+    fn aliases(&self) -> Vec<String> {
+        let mut aliases: Vec<String> = Vec::new();
+        for block in &self.blocks {
+            for pattern in &block.patterns {
+                let concrete = !pattern.starts_with('!') && !pattern.contains(['*', '?']);
+                if concrete && !aliases.contains(pattern) {
+                    aliases.push(pattern.clone());
+                }
+            }
+        }
+        aliases
+    }
+
+    // 按OpenSSH的规则解析主机：按顺序匹配所有 Host 块，每个选项以第一次出现的值为准
+    // IdentityFile 可以出现多次，全部保留
+    fn resolve(&self, alias: &str) -> ResolvedHost {
+        let mut options: HashMap<&str, &Vec<String>> = HashMap::new();
+        let mut identity_files = Vec::new();
+
+        for block in self.blocks.iter().filter(|block| block_matches(block, alias)) {
+            for (keyword, args) in &block.options {
+                if keyword == "identityfile" {
+                    identity_files.extend(args.iter().cloned());
+                } else {
+                    options.entry(keyword.as_str()).or_insert(args);
+                }
+            }
+        }
+
+        let first = |keyword: &str| options.get(keyword).and_then(|args| args.first()).cloned();
+        let host_name = first("hostname").map(|name| name.replace("%h", alias));
+        let user = first("user");
+        let port = first("port").and_then(|port| port.parse().ok());
+
+        // IdentityFile 中的 %h 指实际连接的主机名
+        let tokens = Tokens {
+            host: host_name.clone().unwrap_or_else(|| alias.to_string()),
+            port: port.unwrap_or(DEFAULT_SSH_PORT),
+            user: user.clone().unwrap_or_else(local_username),
+        };
+        let identity_files = identity_files
+            .iter()
+            .filter(|path| !path.eq_ignore_ascii_case("none"))
+            .map(|path| tokens.expand(path))
+            .collect();
+
+        ResolvedHost {
+            host_name,
+            user,
+            port,
+            identity_files,
+            proxy_jump: first("proxyjump").filter(|jump| !jump.eq_ignore_ascii_case("none")),
+        }
+    }
+}
+
+impl ResolvedHost {
+    fn is_empty(&self) -> bool {
+        self.host_name.is_none()
+            && self.user.is_none()
+            && self.port.is_none()
+            && self.identity_files.is_empty()
+            && self.proxy_jump.is_none()
+    }
+
+    // 私钥认证优先，其次ssh-agent
+    fn auth_methods(&self) -> Vec<SshAuthMethod> {
+        let mut methods: Vec<SshAuthMethod> = self.identity_files
+            .iter()
+            .map(|path| SshAuthMethod::KeyFile {
+                private_key_path: path.clone(),
+                public_key_path: None,
+                passphrase: None,
+                passphrase_secret_id: None,
+            })
+            .collect();
+        methods.push(SshAuthMethod::Agent);
+        methods
+    }
+}
+
+// 用 ~/.ssh/config 中的配置补全连接参数，请求中已经指定的值优先
+// host 可以是配置中的别名，此时会替换为真实的 HostName
+pub fn apply_user_config(mut config: SshConnectionConfig) -> EmsResult<SshConnectionConfig> {
+    let Some(ssh_config) = SshConfig::load_user_config()? else {
+        if config.port == 0 {
+            config.port = DEFAULT_SSH_PORT;
+        }
+        return Ok(config);
+    };
+
+    let resolved = ssh_config.resolve(&config.host);
+    if !resolved.is_empty() {
+        println!("🔧 [SSH] 使用SSH配置文件中 {} 的设置", config.host);
+    }
+
+    if config.username.is_empty() {
+        config.username = resolved.user.clone().unwrap_or_else(local_username);
+    }
+    if config.port == 0 {
+        config.port = resolved.port.unwrap_or(DEFAULT_SSH_PORT);
+    }
+    let has_password = config.password.as_ref().is_some_and(|password| !password.is_empty())
+        || config.password_secret_id.is_some();
+    if config.auth_methods.is_empty() && !has_password && !resolved.is_empty() {
+        config.auth_methods = resolved.auth_methods();
+    }
+    if config.jump_hosts.is_empty() {
+        if let Some(ref proxy_jump) = resolved.proxy_jump {
+            config.jump_hosts = parse_proxy_jump(&ssh_config, proxy_jump, &config.username)?;
+        }
+    }
+    if let Some(host_name) = resolved.host_name {
+        config.host = host_name;
+    }
+
+    Ok(config)
+}
+
+// 解析 ProxyJump：逗号分隔的 [user@]host[:port]，每个跳板机同样按配置文件解析
+fn parse_proxy_jump(ssh_config: &SshConfig, proxy_jump: &str, default_user: &str) -> EmsResult<Vec<SshConnectionConfig>> {
+    proxy_jump
+        .split(',')
+        .map(|hop| {
+            let hop = hop.trim();
+            let (user, host_port) = match hop.rsplit_once('@') {
+                Some((user, host_port)) => (Some(user.to_string()), host_port),
+                None => (None, hop),
+            };
+            let (alias, port) = split_host_port(host_port)
+                .ok_or_else(|| EmsError::ParseError(format!("无效的ProxyJump: {}", hop)))?;
+
+            let resolved = ssh_config.resolve(&alias);
+            Ok(SshConnectionConfig {
+                host: resolved.host_name.clone().unwrap_or(alias),
+                port: port.or(resolved.port).unwrap_or(DEFAULT_SSH_PORT),
+                username: user.or(resolved.user.clone()).unwrap_or_else(|| default_user.to_string()),
+                password: None,
+                password_secret_id: None,
+                sudo_password: None,
+                sudo_password_secret_id: None,
+                auth_methods: resolved.auth_methods(),
+                auto_reconnect: false,
+                jump_hosts: Vec::new(),
+            })
+        })
+        .collect()
+}
+
+// 拆分 host:port，支持 [ipv6]:port
+fn split_host_port(value: &str) -> Option<(String, Option<u16>)> {
+    if let Some(rest) = value.strip_prefix('[') {
+        let (host, rest) = rest.split_once(']')?;
+        let port = match rest.strip_prefix(':') {
+            Some(port) => Some(port.parse().ok()?),
+            None if rest.is_empty() => None,
+            None => return None,
+        };
+        return Some((host.to_string(), port));
+    }
+
+    match value.split_once(':') {
+        Some((host, port)) if !host.is_empty() => Some((host.to_string(), Some(port.parse().ok()?))),
+        Some(_) => None,
+        None if value.is_empty() => None,
+        None => Some((value.to_string(), None)),
+    }
+}
+
+// 解析配置文件，遇到 Include 时递归解析
+// patterns 为文件开头所属的 Host 块（被 Include 的文件继承所在块的条件）
+fn parse_file(
+    path: &Path,
+    ssh_dir: &Path,
+    patterns: Vec<String>,
+    depth: usize,
+    blocks: &mut Vec<HostBlock>,
+) -> EmsResult<()> {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(EmsError::ParseError(format!("Include 嵌套过深: {}", path.display())));
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| EmsError::Io(format!("无法读取SSH配置文件 {}: {}", path.display(), e)))?;
+
+    blocks.push(HostBlock { patterns, options: Vec::new() });
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (keyword, rest) = split_keyword(line);
+        let args = split_args(rest).ok_or_else(|| {
+            EmsError::ParseError(format!("{}:{} 引号不匹配", path.display(), index + 1))
+        })?;
+
+        match keyword.to_ascii_lowercase().as_str() {
+            "host" => blocks.push(HostBlock { patterns: args, options: Vec::new() }),
+            // 不支持 Match 条件，其后的选项不会应用到任何主机
+            "match" => blocks.push(HostBlock { patterns: Vec::new(), options: Vec::new() }),
+            "include" => {
+                let current = blocks.last().map(|block| block.patterns.clone()).unwrap_or_default();
+                for include_path in expand_include(&args, ssh_dir) {
+                    parse_file(&include_path, ssh_dir, current.clone(), depth + 1, blocks)?;
+                }
+                // Include 之后的选项仍属于原来的块
+                blocks.push(HostBlock { patterns: current, options: Vec::new() });
+            }
+            keyword => {
+                if let Some(block) = blocks.last_mut() {
+                    block.options.push((keyword.to_string(), args));
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// 拆分关键字和参数，支持 "Keyword value" 和 "Keyword=value" 两种写法
+fn split_keyword(line: &str) -> (&str, &str) {
+    let end = line.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(line.len());
+    let (keyword, rest) = line.split_at(end);
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest).trim_start();
+    (keyword, rest)
+}
+
+// 按空白拆分参数，双引号内的空白保留；引号不匹配时返回 None
+fn split_args(value: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+
+    for c in value.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    if has_arg {
+        args.push(current);
+    }
+    Some(args)
+}
+
+// 展开 Include 的路径：相对路径基于 ~/.ssh，文件名部分支持通配符，结果按名称排序
+fn expand_include(args: &[String], ssh_dir: &Path) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for arg in args {
+        let path = expand_tilde(arg);
+        let path = if path.is_absolute() { path } else { ssh_dir.join(path) };
+
+        let file_pattern = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        if !file_pattern.contains(['*', '?']) {
+            if path.is_file() {
+                paths.push(path);
+            }
+            continue;
+        }
+
+        let Some(dir) = path.parent() else { continue };
+        let Ok(entries) = fs::read_dir(dir) else { continue };
+        let mut matched: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                path.file_name()
+                    .is_some_and(|name| wildcard_match(&file_pattern, &name.to_string_lossy()))
+            })
+            .collect();
+        matched.sort();
+        paths.extend(matched);
+    }
+    paths
+}
+
+// Host 模式匹配：任一否定模式命中则不匹配，否则任一模式命中即匹配
+fn block_matches(block: &HostBlock, host: &str) -> bool {
+    let mut matched = false;
+    for pattern in &block.patterns {
+        if let Some(negated) = pattern.strip_prefix('!') {
+            if wildcard_match(negated, host) {
+                return false;
+            }
+        } else if wildcard_match(pattern, host) {
+            matched = true;
+        }
+    }
+    matched
+}
+
+// 支持 * 和 ? 的通配符匹配，主机名不区分大小写
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // 让 * 多匹配一个字符后重试
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+// IdentityFile 等路径中支持的 % 占位符
+struct Tokens {
+    host: String,
+    port: u16,
+    user: String,
+}
+
+impl Tokens {
+    fn expand(&self, value: &str) -> String {
+        let home = dirs::home_dir().map(|home| home.to_string_lossy().to_string()).unwrap_or_default();
+        let mut result = String::new();
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                result.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => result.push('%'),
+                Some('d') => result.push_str(&home),
+                Some('h') => result.push_str(&self.host),
+                Some('p') => result.push_str(&self.port.to_string()),
+                Some('r') => result.push_str(&self.user),
+                Some('u') => result.push_str(&local_username()),
+                Some(other) => {
+                    result.push('%');
+                    result.push(other);
+                }
+                None => result.push('%'),
+            }
+        }
+        expand_tilde(&result).to_string_lossy().to_string()
+    }
+}
+
+fn expand_tilde(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ if path == "~" => dirs::home_dir().unwrap_or_else(|| PathBuf::from(path)),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn user_ssh_dir() -> Option<PathBuf> {
+    dirs::home_dir().map(|home| home.join(".ssh"))
+}
+
+// 本机当前用户名，配置中未指定 User 时作为默认值（与OpenSSH一致）
+fn local_username() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "root".to_string())
+}
+
+// 列出 ~/.ssh/config 中的主机别名及解析后的配置
+#[tauri::command]
+pub async fn list_ssh_config_hosts() -> EmsResult<Vec<SshConfigHost>> {
+    run_blocking(|| {
+        let Some(ssh_config) = SshConfig::load_user_config()? else {
+            return Ok(Vec::new());
+        };
+
+        let hosts = ssh_config
+            .aliases()
+            .into_iter()
+            .map(|alias| {
+                let resolved = ssh_config.resolve(&alias);
+                SshConfigHost {
+                    host: resolved.host_name.unwrap_or_else(|| alias.clone()),
+                    alias,
+                    port: resolved.port.unwrap_or(DEFAULT_SSH_PORT),
+                    username: resolved.user,
+                    identity_files: resolved.identity_files,
+                    proxy_jump: resolved.proxy_jump,
+                }
+            })
+            .collect();
+        Ok(hosts)
+    }).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 把配置文件写入临时目录（作为 ~/.ssh）后解析 config
+    fn load(files: &[(&str, &str)]) -> EmsResult<SshConfig> {
+        let ssh_dir = std::env::temp_dir().join(format!("ems-ssh-config-{}", uuid::Uuid::new_v4()));
+        for (name, content) in files {
+            let path = ssh_dir.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut blocks = Vec::new();
+        let result = parse_file(&ssh_dir.join("config"), &ssh_dir, vec!["*".to_string()], 0, &mut blocks);
+        fs::remove_dir_all(&ssh_dir).unwrap();
+        result.map(|_| SshConfig { blocks })
+    }
+
+    fn block(patterns: &[&str]) -> HostBlock {
+        HostBlock { patterns: patterns.iter().map(|p| p.to_string()).collect(), options: Vec::new() }
+    }
+
+    #[test]
+    fn matches_wildcards_and_negated_patterns() {
+        assert!(wildcard_match("*.example.com", "db.Example.COM"));
+        assert!(wildcard_match("web?", "web1"));
+        assert!(!wildcard_match("web?", "web10"));
+        assert!(wildcard_match("a*b*c", "axxbyybc"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match("db", "db1"));
+
+        let lan = block(&["*.lan", "!gw.lan"]);
+        assert!(block_matches(&lan, "db.lan"));
+        assert!(!block_matches(&lan, "gw.lan"));
+        assert!(!block_matches(&lan, "db.wan"));
+        // 只有否定模式时没有主机匹配
+        assert!(!block_matches(&block(&["!gw"]), "db"));
+        // Match 块
+        assert!(!block_matches(&block(&[]), "db"));
+    }
+
+    #[test]
+    fn splits_keywords_and_quoted_args() {
+        assert_eq!(split_keyword("HostName db.lan"), ("HostName", "db.lan"));
+        assert_eq!(split_keyword("Port=2222"), ("Port", "2222"));
+        assert_eq!(split_keyword("Port = 2222"), ("Port", "2222"));
+        assert_eq!(split_keyword("Host"), ("Host", ""));
+
+        assert_eq!(split_args(r#"db  "web 1"  cache"#), Some(vec!["db".to_string(), "web 1".to_string(), "cache".to_string()]));
+        assert_eq!(split_args(r#""~/My Keys/id_ed25519""#), Some(vec!["~/My Keys/id_ed25519".to_string()]));
+        assert_eq!(split_args(r#""""#), Some(vec![String::new()]));
+        assert_eq!(split_args(""), Some(Vec::new()));
+        assert_eq!(split_args(r#""unterminated"#), None);
+    }
+
+    #[test]
+    fn splits_host_and_port() {
+        assert_eq!(split_host_port("db"), Some(("db".to_string(), None)));
+        assert_eq!(split_host_port("db:2222"), Some(("db".to_string(), Some(2222))));
+        assert_eq!(split_host_port("[::1]:22"), Some(("::1".to_string(), Some(22))));
+        assert_eq!(split_host_port("[fe80::1]"), Some(("fe80::1".to_string(), None)));
+        assert_eq!(split_host_port(""), None);
+        assert_eq!(split_host_port(":22"), None);
+        assert_eq!(split_host_port("db:ssh"), None);
+        assert_eq!(split_host_port("[::1]22"), None);
+    }
+
+    #[test]
+    fn parses_proxy_jump_hops() {
+        let config = load(&[("config", "Host bastion\n  HostName 10.0.0.1\n  User ops\n  Port 2200\n")]).unwrap();
+
+        let hops = parse_proxy_jump(&config, "bastion, admin@[::1]:2022,gw", "root").unwrap();
+        let hops: Vec<_> = hops.iter().map(|hop| (hop.host.as_str(), hop.port, hop.username.as_str())).collect();
+        assert_eq!(hops, vec![("10.0.0.1", 2200, "ops"), ("::1", 2022, "admin"), ("gw", DEFAULT_SSH_PORT, "root")]);
+
+        assert!(matches!(parse_proxy_jump(&config, "bastion:ssh", "root"), Err(EmsError::ParseError(_))));
+    }
+
+    #[test]
+    fn expands_includes_in_place() {
+        let config = load(&[
+            ("config", "Include conf.d/*.conf\nHost db\n  Include db.conf\n  User ops\nHost *\n  User fallback\n  Port 2022\n"),
+            ("db.conf", "HostName 10.0.0.5\nPort 2200\nIdentityFile /keys/%h_%r\n"),
+            ("conf.d/a.conf", "Host web\n  HostName 10.0.0.6\n"),
+            ("conf.d/b.conf", "Host web\n  HostName 10.0.0.7\n"),
+            ("conf.d/notes.txt", "Host web\n  HostName 10.0.0.8\n"),
+        ]).unwrap();
+
+        assert_eq!(config.aliases(), vec!["web", "db"]);
+
+        // 被 Include 的文件继承所在的 Host 块，之后的选项仍属于该块
+        let db = config.resolve("db");
+        assert_eq!(db.host_name.as_deref(), Some("10.0.0.5"));
+        assert_eq!(db.port, Some(2200));
+        assert_eq!(db.user.as_deref(), Some("ops"));
+        assert_eq!(db.identity_files, vec!["/keys/10.0.0.5_ops"]);
+
+        // 通配符按文件名排序，第一次出现的值为准
+        let web = config.resolve("web");
+        assert_eq!(web.host_name.as_deref(), Some("10.0.0.6"));
+        assert_eq!(web.port, Some(2022));
+        assert_eq!(web.user.as_deref(), Some("fallback"));
+
+        assert!(config.resolve("cache").host_name.is_none());
+    }
+
+    #[test]
+    fn rejects_recursive_includes() {
+        assert!(matches!(load(&[("config", "Include config\n")]), Err(EmsError::ParseError(_))));
+        assert!(matches!(load(&[("config", "Host \"db\n")]), Err(EmsError::ParseError(_))));
+    }
+
+    #[test]
+    fn expands_tokens() {
+        let tokens = Tokens { host: "db.lan".to_string(), port: 2222, user: "ops".to_string() };
+        assert_eq!(
+            tokens.expand("/keys/%r@%h:%p-%u-100%%"),
+            format!("/keys/ops@db.lan:2222-{}-100%", local_username())
+        );
+        // 不认识的占位符原样保留
+        assert_eq!(tokens.expand("/keys/%x%"), "/keys/%x%");
+    }
+}
@@ -0,0 +1,230 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use chrono::Local;
+use tauri::State;
+use serde::{Deserialize, Serialize};
+
+use crate::{AppState, app_config_dir};
+use crate::error::{EmsError, EmsResult};
+use crate::file_transfer::TransferDirection;
+use crate::logger::add_log_internal;
+
+// 续传前比对的末尾数据长度
+const VERIFY_WINDOW: u64 = 64 * 1024;
+
+// 一个未完成的传输
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub transfer_id: String,
+    pub direction: TransferDirection,
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    // 最终的本地路径和远程路径（不含 .part 后缀）
+    pub local_path: String,
+    pub remote_path: String,
+    pub total_bytes: u64,
+    // 源文件的修改时间，源文件变化后不能续传
+    pub source_mtime: Option<u64>,
+    // 最近一次记录的进度，续传时以实际的临时文件大小为准
+    pub bytes_done: u64,
+    pub started_at: String,
+    pub updated_at: String,
+}
+
+impl JournalEntry {
+    fn matches(&self, other: &JournalEntry) -> bool {
+        self.direction == other.direction
+            && self.host == other.host
+            && self.port == other.port
+            && self.username == other.username
+            && self.local_path == other.local_path
+            && self.remote_path == other.remote_path
+    }
+}
+
+// 传输日志，保存在配置目录下的 transfers.json，程序重启后仍然可以续传
+#[derive(Clone)]
+pub struct TransferJournal {
+    entries: Arc<Mutex<Vec<JournalEntry>>>,
+}
+
+impl TransferJournal {
+    fn load() -> Self {
+        let entries = match read_journal() {
+            Ok(entries) => entries,
+            Err(e) => {
+                println!("⚠️ [TRANSFER] 读取传输日志失败: {}", e);
+                Vec::new()
+            }
+        };
+        TransferJournal {
+            entries: Arc::new(Mutex::new(entries)),
+        }
+    }
+
+    // 查找同一文件未完成的传输，源文件大小或修改时间变化时视为新的传输
+    pub fn find_resumable(&self, entry: &JournalEntry) -> EmsResult<Option<JournalEntry>> {
+        let entries = self.entries.lock()?;
+        Ok(entries
+            .iter()
+            .find(|existing| {
+                existing.matches(entry)
+                    && existing.total_bytes == entry.total_bytes
+                    && existing.source_mtime == entry.source_mtime
+            })
+            .cloned())
+    }
+
+    // 记录开始传输，替换同一文件之前的记录
+    pub fn begin(&self, entry: JournalEntry) -> EmsResult<()> {
+        self.update(|entries| {
+            entries.retain(|existing| !existing.matches(&entry));
+            entries.push(entry);
+        })
+    }
+
+    pub fn checkpoint(&self, transfer_id: &str, bytes_done: u64) -> EmsResult<()> {
+        self.update(|entries| {
+            if let Some(entry) = entries.iter_mut().find(|entry| entry.transfer_id == transfer_id) {
+                entry.bytes_done = bytes_done;
+                entry.updated_at = now();
+            }
+        })
+    }
+
+    pub fn remove(&self, transfer_id: &str) -> EmsResult<Option<JournalEntry>> {
+        let mut removed = None;
+        self.update(|entries| {
+            if let Some(index) = entries.iter().position(|entry| entry.transfer_id == transfer_id) {
+                removed = Some(entries.remove(index));
+            }
+        })?;
+        Ok(removed)
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut Vec<JournalEntry>)) -> EmsResult<()> {
+        let mut entries = self.entries.lock()?;
+        apply(&mut entries);
+        write_journal(&entries)
+    }
+}
+
+// 初始化传输日志
+pub fn init_transfer_journal() -> TransferJournal {
+    TransferJournal::load()
+}
+
+fn journal_path() -> EmsResult<PathBuf> {
+    Ok(app_config_dir()?.join("transfers.json"))
+}
+
+fn read_journal() -> EmsResult<Vec<JournalEntry>> {
+    let path = journal_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(&path)?)?)
+}
+
+// 先写临时文件再重命名，避免写入中断导致文件损坏
+fn write_journal(entries: &[JournalEntry]) -> EmsResult<()> {
+    let path = journal_path()?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(entries)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+pub fn now() -> String {
+    Local::now().to_rfc3339()
+}
+
+// 确认临时文件的末尾与源文件相同位置的数据一致，返回可以续传的偏移量
+// 不一致或无法读取时从头开始
+pub fn verified_offset(
+    source: &mut (impl Read + Seek),
+    partial: &mut (impl Read + Seek),
+    partial_len: u64,
+    total_bytes: u64,
+) -> u64 {
+    if partial_len == 0 || partial_len > total_bytes {
+        return 0;
+    }
+    match tails_match(source, partial, partial_len) {
+        Ok(true) => partial_len,
+        Ok(false) => {
+            println!("⚠️ [TRANSFER] 临时文件与源文件不一致，从头开始传输");
+            0
+        }
+        Err(e) => {
+            println!("⚠️ [TRANSFER] 无法校验临时文件，从头开始传输: {}", e);
+            0
+        }
+    }
+}
+
+fn tails_match(source: &mut (impl Read + Seek), partial: &mut (impl Read + Seek), offset: u64) -> io::Result<bool> {
+    let window = offset.min(VERIFY_WINDOW);
+    let start = offset - window;
+    let mut source_tail = vec![0u8; window as usize];
+    let mut partial_tail = vec![0u8; window as usize];
+
+    source.seek(SeekFrom::Start(start))?;
+    source.read_exact(&mut source_tail)?;
+    partial.seek(SeekFrom::Start(start))?;
+    partial.read_exact(&mut partial_tail)?;
+    Ok(source_tail == partial_tail)
+}
+
+// 列出未完成的传输，用同样的参数再次上传/下载即可续传
+#[tauri::command]
+pub async fn list_interrupted_transfers(journal: State<'_, TransferJournal>) -> EmsResult<Vec<JournalEntry>> {
+    Ok(journal.entries.lock()?.clone())
+}
+
+// 放弃未完成的传输，下次从头开始；临时文件在下次传输同一文件时覆盖
+#[tauri::command]
+pub async fn discard_transfer(
+    transfer_id: String,
+    journal: State<'_, TransferJournal>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    let entry = journal
+        .remove(&transfer_id)?
+        .ok_or_else(|| EmsError::NotFound(format!("传输记录不存在: {}", transfer_id)))?;
+    add_log_internal(&app_state, "INFO", &format!("放弃未完成的传输: {} -> {}", entry.local_path, entry.remote_path));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn resumes_only_from_matching_partial_data() {
+        let source: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+
+        let mut partial = Cursor::new(source[..150_000].to_vec());
+        assert_eq!(verified_offset(&mut Cursor::new(&source), &mut partial, 150_000, 200_000), 150_000);
+
+        // 末尾数据被破坏
+        let mut corrupted = source[..150_000].to_vec();
+        corrupted[149_000] ^= 0xff;
+        assert_eq!(verified_offset(&mut Cursor::new(&source), &mut Cursor::new(corrupted), 150_000, 200_000), 0);
+
+        // 临时文件比源文件大，源文件已被替换
+        let mut longer = Cursor::new(source.clone());
+        assert_eq!(verified_offset(&mut Cursor::new(&source[..100]), &mut longer, 200_000, 100), 0);
+
+        let mut empty = Cursor::new(Vec::new());
+        assert_eq!(verified_offset(&mut Cursor::new(&source), &mut empty, 0, 200_000), 0);
+
+        // 比校验窗口短的临时文件
+        let mut short = Cursor::new(source[..10].to_vec());
+        assert_eq!(verified_offset(&mut Cursor::new(&source), &mut short, 10, 200_000), 10);
+    }
+}
@@ -0,0 +1,817 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::{ChaCha20Poly1305, Key, KeyInit, Nonce};
+use chacha20poly1305::aead::{Aead, AeadCore, OsRng, Payload};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chrono::Local;
+use tauri::State;
+use serde::{Deserialize, Serialize};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::{AppState, SshAuthMethod, SshConnectionConfig, app_config_dir};
+use crate::error::{EmsError, EmsResult};
+use crate::ssh::run_blocking;
+use crate::logger::add_log_internal;
+
+// 默认空闲多久后自动锁定
+const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+// 检查空闲状态的间隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+// 主密码最短长度
+const MIN_MASTER_PASSWORD_LEN: usize = 8;
+// 校验主密码时解密的固定内容
+const VERIFIER_PLAINTEXT: &[u8] = b"ems_tools vault";
+const VERIFIER_AAD: &[u8] = b"ems_tools-vault-verifier";
+const VAULT_VERSION: u32 = 1;
+const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+// 会话内存凭据的ID前缀
+const SESSION_SECRET_PREFIX: &str = "session:";
+
+type VaultKey = Zeroizing<[u8; KEY_LEN]>;
+
+// 凭据类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretKind {
+    // SSH登录密码
+    Password,
+    // 私钥口令
+    Passphrase,
+    // sudo密码
+    SudoPassword,
+    // PEM格式的私钥
+    PrivateKey,
+}
+
+// Argon2id 参数，随文件保存以便日后调整
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct KdfParams {
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+}
+
+// ChaCha20-Poly1305 加密后的数据
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Sealed {
+    nonce: String,
+    ciphertext: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct VaultEntry {
+    id: String,
+    label: String,
+    kind: SecretKind,
+    created_at: String,
+    updated_at: String,
+    sealed: Sealed,
+}
+
+// vault.json 的内容，只有凭据本身是加密的
+#[derive(Debug, Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    kdf: KdfParams,
+    verifier: Sealed,
+    entries: Vec<VaultEntry>,
+}
+
+// 返回给前端的凭据信息，不包含凭据内容
+#[derive(Debug, Serialize)]
+pub struct SecretInfo {
+    id: String,
+    label: String,
+    kind: SecretKind,
+    created_at: String,
+    updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VaultStatus {
+    initialized: bool,
+    unlocked: bool,
+    idle_timeout_secs: u64,
+}
+
+struct VaultInner {
+    // 解锁后保存在内存中的密钥，锁定时清零
+    key: Option<VaultKey>,
+    last_used: Instant,
+    idle_timeout: Duration,
+}
+
+impl VaultInner {
+    fn lock(&mut self) {
+        // Zeroizing 在释放时清零
+        self.key = None;
+    }
+
+    // 取得密钥并刷新空闲计时，已超时则先锁定
+    fn key(&mut self) -> EmsResult<&VaultKey> {
+        if self.last_used.elapsed() >= self.idle_timeout {
+            self.lock();
+        }
+        self.last_used = Instant::now();
+        self.key.as_ref().ok_or(EmsError::VaultLocked)
+    }
+}
+
+// 已连接会话使用的明文凭据，用进程启动时随机生成的密钥加密，只保存在内存中
+struct SessionSecrets {
+    key: VaultKey,
+    entries: HashMap<String, Sealed>,
+}
+
+// 加密的凭据保险库，保存在配置目录下的 vault.json
+#[derive(Clone)]
+pub struct Vault {
+    inner: Arc<Mutex<VaultInner>>,
+    session_secrets: Arc<Mutex<SessionSecrets>>,
+}
+
+impl Vault {
+    fn new() -> Self {
+        Vault {
+            inner: Arc::new(Mutex::new(VaultInner {
+                key: None,
+                last_used: Instant::now(),
+                idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            })),
+            session_secrets: Arc::new(Mutex::new(SessionSecrets {
+                key: random_key(),
+                entries: HashMap::new(),
+            })),
+        }
+    }
+
+    fn status(&self) -> EmsResult<VaultStatus> {
+        let mut inner = self.inner.lock()?;
+        if inner.last_used.elapsed() >= inner.idle_timeout {
+            inner.lock();
+        }
+        Ok(VaultStatus {
+            initialized: vault_path()?.exists(),
+            unlocked: inner.key.is_some(),
+            idle_timeout_secs: inner.idle_timeout.as_secs(),
+        })
+    }
+
+    // 创建新的保险库，已存在时返回错误
+    fn initialize(&self, master_password: &str) -> EmsResult<()> {
+        if master_password.chars().count() < MIN_MASTER_PASSWORD_LEN {
+            return Err(EmsError::InvalidInput(format!("主密码至少需要 {} 个字符", MIN_MASTER_PASSWORD_LEN)));
+        }
+
+        let mut inner = self.inner.lock()?;
+        if vault_path()?.exists() {
+            return Err(EmsError::InvalidInput("凭据保险库已存在".to_string()));
+        }
+
+        let (file, key) = create_vault_file(master_password, &Params::default())?;
+        write_vault(&file)?;
+
+        inner.key = Some(key);
+        inner.last_used = Instant::now();
+        Ok(())
+    }
+
+    // 用主密码解锁，idle_timeout 为空时保持当前设置
+    fn unlock(&self, master_password: &str, idle_timeout: Option<Duration>) -> EmsResult<()> {
+        let file = read_vault()?;
+        let key = verify_master_password(master_password, &file)?;
+
+        let mut inner = self.inner.lock()?;
+        inner.key = Some(key);
+        inner.last_used = Instant::now();
+        if let Some(idle_timeout) = idle_timeout {
+            inner.idle_timeout = idle_timeout;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> EmsResult<()> {
+        self.inner.lock()?.lock();
+        Ok(())
+    }
+
+    // 空闲超时后锁定，返回是否执行了锁定
+    fn lock_if_idle(&self) -> bool {
+        match self.inner.lock() {
+            Ok(mut inner) if inner.key.is_some() && inner.last_used.elapsed() >= inner.idle_timeout => {
+                inner.lock();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn list(&self) -> EmsResult<Vec<SecretInfo>> {
+        let _inner = self.inner.lock()?;
+        if !vault_path()?.exists() {
+            return Ok(Vec::new());
+        }
+        let file = read_vault()?;
+        Ok(file.entries
+            .into_iter()
+            .map(|entry| SecretInfo {
+                id: entry.id,
+                label: entry.label,
+                kind: entry.kind,
+                created_at: entry.created_at,
+                updated_at: entry.updated_at,
+            })
+            .collect())
+    }
+
+    // 保存新的凭据，返回凭据ID
+    pub fn store(&self, label: &str, kind: SecretKind, secret: &str) -> EmsResult<String> {
+        let mut inner = self.inner.lock()?;
+        let key = inner.key()?;
+        let mut file = read_vault()?;
+
+        let id = uuid::Uuid::new_v4().to_string();
+        let timestamp = Local::now().to_rfc3339();
+        file.entries.push(VaultEntry {
+            sealed: seal(key, secret.as_bytes(), id.as_bytes())?,
+            id: id.clone(),
+            label: label.to_string(),
+            kind,
+            created_at: timestamp.clone(),
+            updated_at: timestamp,
+        });
+        write_vault(&file)?;
+        Ok(id)
+    }
+
+    // 替换已有凭据的内容
+    pub fn update(&self, id: &str, secret: &str) -> EmsResult<()> {
+        let mut inner = self.inner.lock()?;
+        let key = inner.key()?;
+        let mut file = read_vault()?;
+
+        let entry = file.entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .ok_or_else(|| EmsError::NotFound(format!("凭据不存在: {}", id)))?;
+        entry.sealed = seal(key, secret.as_bytes(), id.as_bytes())?;
+        entry.updated_at = Local::now().to_rfc3339();
+        write_vault(&file)
+    }
+
+    // 删除凭据，不需要解锁
+    pub fn remove(&self, ids: &[String]) -> EmsResult<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let _inner = self.inner.lock()?;
+        if !vault_path()?.exists() {
+            return Ok(());
+        }
+        let mut file = read_vault()?;
+        file.entries.retain(|entry| !ids.contains(&entry.id));
+        write_vault(&file)
+    }
+
+    // 解密凭据，返回值释放时清零
+    pub fn reveal(&self, id: &str) -> EmsResult<Zeroizing<String>> {
+        let plaintext = if id.starts_with(SESSION_SECRET_PREFIX) {
+            let session_secrets = self.session_secrets.lock()?;
+            let sealed = session_secrets.entries
+                .get(id)
+                .ok_or_else(|| EmsError::NotFound(format!("凭据不存在: {}", id)))?;
+            Zeroizing::new(open(&session_secrets.key, sealed, id.as_bytes())?)
+        } else {
+            let mut inner = self.inner.lock()?;
+            let key = inner.key()?;
+            let file = read_vault()?;
+
+            let entry = file.entries
+                .iter()
+                .find(|entry| entry.id == id)
+                .ok_or_else(|| EmsError::NotFound(format!("凭据不存在: {}", id)))?;
+            Zeroizing::new(open(key, &entry.sealed, id.as_bytes())?)
+        };
+        let secret = std::str::from_utf8(&plaintext)
+            .map_err(|_| EmsError::ParseError("凭据内容不是有效的UTF-8".to_string()))?;
+        Ok(Zeroizing::new(secret.to_string()))
+    }
+
+    // 生成用于连接的配置：按ID从保险库取出密码和私钥口令
+    // 配置中没有引用凭据时不需要解锁
+    pub fn resolve_config(&self, config: &SshConnectionConfig) -> EmsResult<SshConnectionConfig> {
+        let mut resolved = config.clone();
+        self.resolve_in_place(&mut resolved)?;
+        Ok(resolved)
+    }
+
+    fn resolve_in_place(&self, config: &mut SshConnectionConfig) -> EmsResult<()> {
+        if let Some(ref id) = config.password_secret_id {
+            config.password = Some(self.reveal(id)?.to_string());
+        }
+        for method in &mut config.auth_methods {
+            match method {
+                SshAuthMethod::Password { password, password_secret_id: Some(id) } => {
+                    *password = self.reveal(id)?.to_string();
+                }
+                SshAuthMethod::KeyFile { passphrase, passphrase_secret_id: Some(id), .. } => {
+                    *passphrase = Some(self.reveal(id)?.to_string());
+                }
+                SshAuthMethod::KeyMemory { private_key, private_key_secret_id, passphrase, passphrase_secret_id } => {
+                    if let Some(id) = private_key_secret_id {
+                        *private_key = self.reveal(id)?.to_string();
+                    }
+                    if let Some(id) = passphrase_secret_id {
+                        *passphrase = Some(self.reveal(id)?.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        for hop in &mut config.jump_hosts {
+            self.resolve_in_place(hop)?;
+        }
+        Ok(())
+    }
+
+    // 把配置中的明文密码、私钥和口令移入保险库，只保留凭据ID
+    // 已经引用了凭据的会更新原凭据
+    pub fn seal_config(&self, config: &mut SshConnectionConfig, label: &str) -> EmsResult<()> {
+        seal_secrets(config, label, &mut |id, label, kind, secret| self.upsert(id, label, kind, secret))
+    }
+
+    // 把直接连接时提供的明文凭据加密保存在内存中，供已连接的会话在重连和sudo时取出
+    // 不需要保险库解锁，断开连接时由 release_session_secrets 清除
+    pub fn seal_session_config(&self, config: &mut SshConnectionConfig) -> EmsResult<()> {
+        let label = config.host.clone();
+        seal_secrets(config, &label, &mut |_, _, _, secret| self.store_session_secret(secret))
+    }
+
+    fn store_session_secret(&self, secret: &str) -> EmsResult<String> {
+        let mut session_secrets = self.session_secrets.lock()?;
+        let id = format!("{}{}", SESSION_SECRET_PREFIX, uuid::Uuid::new_v4());
+        let sealed = seal(&session_secrets.key, secret.as_bytes(), id.as_bytes())?;
+        session_secrets.entries.insert(id.clone(), sealed);
+        Ok(id)
+    }
+
+    // 清除会话配置引用的内存凭据
+    pub fn release_session_secrets(&self, config: &SshConnectionConfig) {
+        if let Ok(mut session_secrets) = self.session_secrets.lock() {
+            for id in secret_ids(config) {
+                session_secrets.entries.remove(&id);
+            }
+        }
+    }
+
+    fn upsert(&self, id: Option<&str>, label: &str, kind: SecretKind, secret: &str) -> EmsResult<String> {
+        match id {
+            Some(id) => {
+                self.update(id, secret)?;
+                Ok(id.to_string())
+            }
+            None => self.store(label, kind, secret),
+        }
+    }
+}
+
+// 保存一项凭据并返回凭据ID，参数为原凭据ID、名称、类型和凭据内容
+type PutSecret<'a> = dyn FnMut(Option<&str>, &str, SecretKind, &str) -> EmsResult<String> + 'a;
+
+// 把配置中的明文密码、私钥和口令交给 put 保存，配置中只保留返回的凭据ID
+fn seal_secrets(config: &mut SshConnectionConfig, label: &str, put: &mut PutSecret) -> EmsResult<()> {
+    if let Some(mut password) = config.password.take().filter(|password| !password.is_empty()) {
+        config.password_secret_id = Some(put(
+            config.password_secret_id.as_deref(),
+            &format!("{} 登录密码", label),
+            SecretKind::Password,
+            &password,
+        )?);
+        password.zeroize();
+    }
+
+    if let Some(mut sudo_password) = config.sudo_password.take().filter(|password| !password.is_empty()) {
+        config.sudo_password_secret_id = Some(put(
+            config.sudo_password_secret_id.as_deref(),
+            &format!("{} sudo密码", label),
+            SecretKind::SudoPassword,
+            &sudo_password,
+        )?);
+        sudo_password.zeroize();
+    }
+
+    for method in &mut config.auth_methods {
+        match method {
+            SshAuthMethod::Password { password, password_secret_id } if !password.is_empty() => {
+                *password_secret_id = Some(put(
+                    password_secret_id.as_deref(),
+                    &format!("{} 登录密码", label),
+                    SecretKind::Password,
+                    password,
+                )?);
+                password.zeroize();
+                password.clear();
+            }
+            SshAuthMethod::KeyMemory { private_key, private_key_secret_id, passphrase, passphrase_secret_id } => {
+                if !private_key.is_empty() {
+                    *private_key_secret_id = Some(put(
+                        private_key_secret_id.as_deref(),
+                        &format!("{} 私钥", label),
+                        SecretKind::PrivateKey,
+                        private_key,
+                    )?);
+                    private_key.zeroize();
+                    private_key.clear();
+                }
+                if let Some(mut value) = passphrase.take().filter(|value| !value.is_empty()) {
+                    *passphrase_secret_id = Some(put(
+                        passphrase_secret_id.as_deref(),
+                        &format!("{} 私钥口令", label),
+                        SecretKind::Passphrase,
+                        &value,
+                    )?);
+                    value.zeroize();
+                }
+            }
+            SshAuthMethod::KeyFile { passphrase, passphrase_secret_id, .. } => {
+                if let Some(mut value) = passphrase.take().filter(|value| !value.is_empty()) {
+                    *passphrase_secret_id = Some(put(
+                        passphrase_secret_id.as_deref(),
+                        &format!("{} 私钥口令", label),
+                        SecretKind::Passphrase,
+                        &value,
+                    )?);
+                    value.zeroize();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for hop in &mut config.jump_hosts {
+        let hop_label = format!("{} 跳板机 {}", label, hop.host);
+        seal_secrets(hop, &hop_label, put)?;
+    }
+    Ok(())
+}
+
+// 配置中引用的所有凭据ID，包括跳板机的
+pub fn secret_ids(config: &SshConnectionConfig) -> Vec<String> {
+    let mut ids: Vec<String> = config.password_secret_id
+        .iter()
+        .chain(config.sudo_password_secret_id.iter())
+        .cloned()
+        .collect();
+    for method in &config.auth_methods {
+        match method {
+            SshAuthMethod::Password { password_secret_id: Some(id), .. }
+            | SshAuthMethod::KeyFile { passphrase_secret_id: Some(id), .. } => ids.push(id.clone()),
+            SshAuthMethod::KeyMemory { private_key_secret_id, passphrase_secret_id, .. } => {
+                ids.extend(private_key_secret_id.iter().chain(passphrase_secret_id.iter()).cloned());
+            }
+            _ => {}
+        }
+    }
+    for hop in &config.jump_hosts {
+        ids.extend(secret_ids(hop));
+    }
+    ids
+}
+
+// 初始化保险库状态，并启动空闲自动锁定的后台线程
+pub fn init_vault_state() -> Vault {
+    let vault = Vault::new();
+    let watcher = vault.clone();
+    thread::spawn(move || loop {
+        thread::sleep(IDLE_CHECK_INTERVAL);
+        if watcher.lock_if_idle() {
+            println!("🔧 [VAULT] 空闲超时，凭据保险库已自动锁定");
+        }
+    });
+    vault
+}
+
+fn vault_path() -> EmsResult<PathBuf> {
+    Ok(app_config_dir()?.join("vault.json"))
+}
+
+fn read_vault() -> EmsResult<VaultFile> {
+    let path = vault_path()?;
+    if !path.exists() {
+        return Err(EmsError::NotFound("凭据保险库尚未创建，请先设置主密码".to_string()));
+    }
+    let file: VaultFile = serde_json::from_str(&fs::read_to_string(&path)?)?;
+    if file.version != VAULT_VERSION {
+        return Err(EmsError::ParseError(format!("不支持的保险库版本: {}", file.version)));
+    }
+    Ok(file)
+}
+
+// 先写临时文件再重命名，避免写入中断导致文件损坏
+fn write_vault(file: &VaultFile) -> EmsResult<()> {
+    let path = vault_path()?;
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_string_pretty(file)?)?;
+    fs::rename(&temp_path, &path)?;
+    Ok(())
+}
+
+// 生成空的保险库内容和对应的密钥
+fn create_vault_file(master_password: &str, params: &Params) -> EmsResult<(VaultFile, VaultKey)> {
+    let mut salt = [0u8; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let kdf = KdfParams {
+        salt: STANDARD.encode(salt),
+        memory_kib: params.m_cost(),
+        iterations: params.t_cost(),
+        parallelism: params.p_cost(),
+    };
+
+    let key = derive_key(master_password, &kdf)?;
+    let file = VaultFile {
+        version: VAULT_VERSION,
+        verifier: seal(&key, VERIFIER_PLAINTEXT, VERIFIER_AAD)?,
+        kdf,
+        entries: Vec::new(),
+    };
+    Ok((file, key))
+}
+
+// 派生密钥并用校验数据确认主密码正确
+fn verify_master_password(master_password: &str, file: &VaultFile) -> EmsResult<VaultKey> {
+    let key = derive_key(master_password, &file.kdf)?;
+    open(&key, &file.verifier, VERIFIER_AAD)
+        .map_err(|_| EmsError::AuthFailed("主密码错误".to_string()))?;
+    Ok(key)
+}
+
+// 用 Argon2id 从主密码派生密钥
+fn derive_key(master_password: &str, kdf: &KdfParams) -> EmsResult<VaultKey> {
+    let salt = STANDARD.decode(&kdf.salt)
+        .map_err(|e| EmsError::ParseError(format!("保险库盐值无效: {}", e)))?;
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(KEY_LEN))
+        .map_err(|e| EmsError::ParseError(format!("保险库参数无效: {}", e)))?;
+
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(master_password.as_bytes(), &salt, key.as_mut())
+        .map_err(|e| EmsError::InvalidInput(format!("无法派生密钥: {}", e)))?;
+    Ok(key)
+}
+
+// 使用随机nonce加密，aad 绑定凭据ID，防止密文被挪用到其他条目
+fn random_key() -> VaultKey {
+    let mut key = Zeroizing::new([0u8; KEY_LEN]);
+    OsRng.fill_bytes(key.as_mut());
+    key
+}
+
+fn seal(key: &VaultKey, plaintext: &[u8], aad: &[u8]) -> EmsResult<Sealed> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key.as_ref()));
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, Payload { msg: plaintext, aad })
+        .map_err(|_| EmsError::InvalidInput("加密凭据失败".to_string()))?;
+    Ok(Sealed {
+        nonce: STANDARD.encode(nonce),
+        ciphertext: STANDARD.encode(ciphertext),
+    })
+}
+
+fn open(key: &VaultKey, sealed: &Sealed, aad: &[u8]) -> EmsResult<Vec<u8>> {
+    let nonce = STANDARD.decode(&sealed.nonce)
+        .map_err(|e| EmsError::ParseError(format!("凭据nonce无效: {}", e)))?;
+    let ciphertext = STANDARD.decode(&sealed.ciphertext)
+        .map_err(|e| EmsError::ParseError(format!("凭据密文无效: {}", e)))?;
+    if nonce.len() != 12 {
+        return Err(EmsError::ParseError("凭据nonce长度无效".to_string()));
+    }
+
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key.as_ref()));
+    cipher.decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad })
+        .map_err(|_| EmsError::ParseError("凭据解密失败，数据可能已损坏".to_string()))
+}
+
+// 查询保险库状态
+#[tauri::command]
+pub async fn vault_status(vault: State<'_, Vault>) -> EmsResult<VaultStatus> {
+    vault.status()
+}
+
+// 设置主密码并创建保险库
+#[tauri::command]
+pub async fn init_vault(
+    master_password: String,
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    let master_password = Zeroizing::new(master_password);
+    let vault = vault.inner().clone();
+    // 密钥派生较慢，放到阻塞线程执行
+    run_blocking(move || vault.initialize(&master_password)).await?;
+
+    add_log_internal(&app_state, "INFO", "已创建凭据保险库");
+    println!("✅ [VAULT] 已创建凭据保险库");
+    Ok(())
+}
+
+// 用主密码解锁，可同时设置空闲自动锁定时间
+#[tauri::command]
+pub async fn unlock_vault(
+    master_password: String,
+    idle_timeout_secs: Option<u64>,
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    let master_password = Zeroizing::new(master_password);
+    let idle_timeout = idle_timeout_secs.map(Duration::from_secs);
+    let vault = vault.inner().clone();
+    let result = run_blocking(move || vault.unlock(&master_password, idle_timeout)).await;
+
+    match result {
+        Ok(()) => {
+            add_log_internal(&app_state, "INFO", "凭据保险库已解锁");
+            Ok(())
+        }
+        Err(e) => {
+            add_log_internal(&app_state, "WARN", &format!("解锁凭据保险库失败: {}", e));
+            Err(e)
+        }
+    }
+}
+
+// 立即锁定
+#[tauri::command]
+pub async fn lock_vault(
+    vault: State<'_, Vault>,
+    app_state: State<'_, AppState>,
+) -> EmsResult<()> {
+    vault.lock()?;
+    add_log_internal(&app_state, "INFO", "凭据保险库已锁定");
+    Ok(())
+}
+
+// 列出保存的凭据（不含内容）
+#[tauri::command]
+pub async fn list_secrets(vault: State<'_, Vault>) -> EmsResult<Vec<SecretInfo>> {
+    vault.list()
+}
+
+// 保存凭据，例如sudo密码，返回凭据ID
+#[tauri::command]
+pub async fn store_secret(
+    label: String,
+    kind: SecretKind,
+    secret: String,
+    vault: State<'_, Vault>,
+) -> EmsResult<String> {
+    let secret = Zeroizing::new(secret);
+    vault.store(&label, kind, &secret)
+}
+
+// 修改凭据内容
+#[tauri::command]
+pub async fn update_secret(
+    id: String,
+    secret: String,
+    vault: State<'_, Vault>,
+) -> EmsResult<()> {
+    let secret = Zeroizing::new(secret);
+    vault.update(&id, &secret)
+}
+
+// 删除凭据
+#[tauri::command]
+pub async fn delete_secret(
+    id: String,
+    vault: State<'_, Vault>,
+) -> EmsResult<()> {
+    vault.remove(&[id])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试用的低成本参数
+    fn test_params() -> Params {
+        Params::new(Params::MIN_M_COST, 1, 1, Some(KEY_LEN)).unwrap()
+    }
+
+    #[test]
+    fn seals_and_opens_secrets() {
+        let (file, key) = create_vault_file("correct horse", &test_params()).unwrap();
+        let sealed = seal(&key, b"s3cret", b"entry-a").unwrap();
+        assert_eq!(open(&key, &sealed, b"entry-a").unwrap(), b"s3cret");
+
+        // 同一主密码重新派生的密钥可以解密
+        let key = verify_master_password("correct horse", &file).unwrap();
+        assert_eq!(open(&key, &sealed, b"entry-a").unwrap(), b"s3cret");
+
+        // 每次加密使用不同的nonce
+        let again = seal(&key, b"s3cret", b"entry-a").unwrap();
+        assert_ne!(sealed.nonce, again.nonce);
+    }
+
+    #[test]
+    fn rejects_wrong_master_password() {
+        let (file, _) = create_vault_file("correct horse", &test_params()).unwrap();
+        let error = verify_master_password("battery staple", &file).unwrap_err();
+        assert_eq!(error.kind(), "AuthFailed");
+    }
+
+    #[test]
+    fn ciphertext_is_bound_to_entry_id() {
+        let (_, key) = create_vault_file("correct horse", &test_params()).unwrap();
+        let first = seal(&key, b"first", b"entry-a").unwrap();
+        let second = seal(&key, b"second", b"entry-b").unwrap();
+
+        // 交换两个条目的密文后都无法解密
+        assert_eq!(open(&key, &first, b"entry-b").unwrap_err().kind(), "ParseError");
+        assert_eq!(open(&key, &second, b"entry-a").unwrap_err().kind(), "ParseError");
+        // 校验数据也不能当作凭据使用
+        let (file, key) = create_vault_file("correct horse", &test_params()).unwrap();
+        assert!(open(&key, &file.verifier, b"entry-a").is_err());
+    }
+
+    #[test]
+    fn locks_after_idle_timeout() {
+        let vault = Vault::new();
+        let (_, key) = create_vault_file("correct horse", &test_params()).unwrap();
+        {
+            let mut inner = vault.inner.lock().unwrap();
+            inner.key = Some(key);
+            inner.idle_timeout = Duration::from_millis(50);
+            inner.last_used = Instant::now();
+            // 使用时刷新空闲计时
+            assert!(inner.key().is_ok());
+        }
+        assert!(!vault.lock_if_idle());
+
+        thread::sleep(Duration::from_millis(60));
+        assert!(vault.lock_if_idle());
+        let mut inner = vault.inner.lock().unwrap();
+        assert!(matches!(inner.key(), Err(EmsError::VaultLocked)));
+    }
+
+    #[test]
+    fn expired_key_is_not_returned() {
+        let vault = Vault::new();
+        let (_, key) = create_vault_file("correct horse", &test_params()).unwrap();
+        let mut inner = vault.inner.lock().unwrap();
+        inner.key = Some(key);
+        inner.idle_timeout = Duration::ZERO;
+        // 后台线程还没来得及锁定时，取密钥也会先锁定
+        assert!(matches!(inner.key(), Err(EmsError::VaultLocked)));
+        assert!(inner.key.is_none());
+    }
+
+    #[test]
+    fn keeps_session_secrets_sealed_until_released() {
+        let vault = Vault::new();
+        let mut config: SshConnectionConfig = serde_json::from_value(serde_json::json!({
+            "host": "10.0.0.5",
+            "username": "ems",
+            "password": "login-secret",
+            "sudo_password": "sudo-secret",
+            "jump_hosts": [{
+                "host": "bastion",
+                "auth_methods": [{ "type": "key_memory", "private_key": "PEM-secret", "passphrase": "pass-secret" }],
+            }],
+        })).unwrap();
+        vault.seal_session_config(&mut config).unwrap();
+
+        // 会话中只保留凭据ID，日志输出也不含明文
+        let debug = format!("{:?}", config);
+        for secret in ["login-secret", "sudo-secret", "PEM-secret", "pass-secret"] {
+            assert!(!debug.contains(secret), "{}", debug);
+        }
+        assert!(config.password.is_none() && config.sudo_password.is_none());
+
+        // 保险库锁定时同样可以取出，用于重连和sudo
+        let resolved = vault.resolve_config(&config).unwrap();
+        assert_eq!(resolved.login_password(), Some("login-secret"));
+        assert_eq!(vault.reveal(config.sudo_password_secret_id.as_deref().unwrap()).unwrap().as_str(), "sudo-secret");
+        match &resolved.jump_hosts[0].auth_methods[0] {
+            SshAuthMethod::KeyMemory { private_key, passphrase, .. } => {
+                assert_eq!(private_key, "PEM-secret");
+                assert_eq!(passphrase.as_deref(), Some("pass-secret"));
+            }
+            other => panic!("unexpected auth method: {:?}", other),
+        }
+        // 重新解析后的配置输出日志时同样不含明文
+        assert!(!format!("{:?}", resolved).contains("login-secret"));
+
+        vault.release_session_secrets(&config);
+        assert_eq!(vault.resolve_config(&config).unwrap_err().kind(), "NotFound");
+    }
+}
@@ -33,12 +33,30 @@ const router = createRouter({
           component: () => import('@/views/Services/index.vue'),
           meta: { title: '服务管理', icon: 'Tools' }
         },
+        {
+          path: '/port-forward',
+          name: 'PortForward',
+          component: () => import('@/views/PortForward/index.vue'),
+          meta: { title: '端口转发', icon: 'Share' }
+        },
+        {
+          path: '/vault',
+          name: 'Vault',
+          component: () => import('@/views/Vault/index.vue'),
+          meta: { title: '凭据保险库', icon: 'Lock' }
+        },
         {
           path: '/terminal',
           name: 'Terminal',
           component: () => import('@/views/Terminal/index.vue'),
           meta: { title: '终端控制台', icon: 'Monitor' }
         },
+        {
+          path: '/recordings',
+          name: 'Recordings',
+          component: () => import('@/views/Recordings/index.vue'),
+          meta: { title: '终端录制', icon: 'VideoCamera' }
+        },
         {
           path: '/logs',
           name: 'Logs',
@@ -2,6 +2,7 @@ import { defineStore } from 'pinia'
 import { ref } from 'vue'
 import { invoke } from '@tauri-apps/api/tauri'
 import { ElMessage } from 'element-plus'
+import { formatError } from '@/utils/error'
 
 export interface LogEntry {
   timestamp: string
@@ -20,7 +21,7 @@ export const useLogsStore = defineStore('logs', () => {
       logs.value = result
     } catch (error) {
       console.error('获取日志失败:', error)
-      ElMessage.error(`获取日志失败: ${error}`)
+      ElMessage.error(`获取日志失败: ${formatError(error)}`)
     } finally {
       isLoading.value = false
     }
@@ -41,7 +42,7 @@ export const useLogsStore = defineStore('logs', () => {
       logs.value = []
       ElMessage.success('日志已清空')
     } catch (error) {
-      ElMessage.error(`清空日志失败: ${error}`)
+      ElMessage.error(`清空日志失败: ${formatError(error)}`)
     }
   }
 
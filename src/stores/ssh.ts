@@ -1,13 +1,52 @@
 import { defineStore } from 'pinia'
 import { ref, computed } from 'vue'
 import { invoke } from '@tauri-apps/api/tauri'
-import { ElMessage } from 'element-plus'
+import { listen } from '@tauri-apps/api/event'
+import { ElMessage, ElMessageBox } from 'element-plus'
+import { formatError, isEmsError, type EmsError } from '@/utils/error'
+
+export type SshAuthMethod =
+  | { type: 'password'; password: string }
+  | { type: 'key_file'; private_key_path: string; public_key_path?: string; passphrase?: string }
+  | { type: 'key_memory'; private_key: string; passphrase?: string }
+  | { type: 'agent' }
+  | { type: 'keyboard_interactive' }
 
 export interface SshConfig {
   host: string
   port: number
   username: string
   password: string
+  auth_methods?: SshAuthMethod[]
+  // 非root用户执行特权命令时使用，未填写时使用登录密码
+  sudo_password?: string
+  auto_reconnect?: boolean
+  // 依次经过的跳板机
+  jump_hosts?: SshConfig[]
+}
+
+export interface ConnectResult {
+  session_id: string
+  message: string
+  auth_method: string
+}
+
+export interface ConnectionStateEvent {
+  session_id: string
+  state: 'connected' | 'disconnected' | 'reconnecting' | 'failed'
+  attempt: number | null
+  message: string | null
+}
+
+// keyboard-interactive 认证提示
+export interface AuthPromptEvent {
+  prompt_id: string
+  host: string
+  port: number
+  username: string
+  instructions: string
+  prompts: { text: string; echo: boolean }[]
+  timeout_secs: number
 }
 
 export interface SshHistoryItem {
@@ -21,14 +60,41 @@ export interface SshHistoryItem {
   createdAt: string
 }
 
+// 后端保存的连接配置（不含密码）
+export interface ProfileSummary {
+  id: string
+  name: string
+  group: string | null
+  tags: string[]
+  host: string
+  port: number
+  username: string
+  auth_methods: string[]
+  jump_hosts: string[]
+  auto_reconnect: boolean
+  created_at: string
+  updated_at: string
+  last_used: string | null
+}
+
+export interface ProfileInput {
+  name: string
+  group?: string
+  tags: string[]
+  // 修改时省略表示只更新名称、分组和标签
+  config?: SshConfig
+}
+
 const HISTORY_STORAGE_KEY = 'ssh_connection_history'
 
 export const useSshStore = defineStore('ssh', () => {
   const isConnected = ref(false)
+  const sessionId = ref<string | null>(null)
   const connectionConfig = ref<SshConfig | null>(null)
   const connectionStatus = ref('未连接')
   const isConnecting = ref(false)
   const connectionHistory = ref<SshHistoryItem[]>([])
+  const profiles = ref<ProfileSummary[]>([])
 
   // 初始化时加载历史记录
   const loadHistory = () => {
@@ -52,6 +118,82 @@ export const useSshStore = defineStore('ssh', () => {
     }
   }
 
+  // 后台健康监控推送的连接状态变化
+  listen<ConnectionStateEvent>('ssh-connection-state', (event) => {
+    const { session_id, state, attempt, message } = event.payload
+    if (session_id !== sessionId.value) {
+      return
+    }
+
+    switch (state) {
+      case 'disconnected':
+        isConnected.value = false
+        connectionStatus.value = '连接已断开'
+        break
+      case 'reconnecting':
+        connectionStatus.value = `重连中 (第 ${attempt} 次)...`
+        break
+      case 'connected':
+        isConnected.value = true
+        connectionStatus.value = '已连接'
+        ElMessage.success('SSH连接已恢复')
+        break
+      case 'failed':
+        sessionId.value = null
+        isConnected.value = false
+        connectionConfig.value = null
+        connectionStatus.value = '连接已断开'
+        ElMessage.error(message ?? '自动重连失败')
+        break
+    }
+  })
+
+  // keyboard-interactive 认证时服务器要求输入的内容，例如一次性验证码
+  let activePromptId: string | null = null
+
+  listen<AuthPromptEvent>('ssh-auth-prompt', async (event) => {
+    const { prompt_id, host, port, username, instructions, prompts, timeout_secs } = event.payload
+    activePromptId = prompt_id
+
+    const responses: string[] = []
+    try {
+      for (const prompt of prompts) {
+        const { value } = await ElMessageBox.prompt(
+          [instructions, prompt.text].filter((text) => text.trim()).join('\n'),
+          `${username}@${host}:${port} 需要验证（${timeout_secs} 秒内输入）`,
+          {
+            inputType: prompt.echo ? 'text' : 'password',
+            confirmButtonText: '确定',
+            cancelButtonText: '取消',
+            closeOnClickModal: false
+          }
+        )
+        responses.push(value ?? '')
+      }
+    } catch {
+      // 用户取消，或后端超时后关闭了对话框
+      if (activePromptId === prompt_id) {
+        activePromptId = null
+        invoke('answer_auth_prompt', { promptId: prompt_id, responses: null }).catch(() => {})
+      }
+      return
+    }
+
+    activePromptId = null
+    try {
+      await invoke('answer_auth_prompt', { promptId: prompt_id, responses })
+    } catch (error) {
+      ElMessage.error(formatError(error))
+    }
+  })
+
+  listen<string>('ssh-auth-prompt-closed', (event) => {
+    if (activePromptId === event.payload) {
+      activePromptId = null
+      ElMessageBox.close()
+    }
+  })
+
   const connectionInfo = computed(() => {
     if (!isConnected.value || !connectionConfig.value) {
       return '未连接'
@@ -59,13 +201,72 @@ export const useSshStore = defineStore('ssh', () => {
     return `已连接到 ${connectionConfig.value.host}:${connectionConfig.value.port}`
   })
 
-  const connect = async (config: SshConfig, saveToHistory = true) => {
+  // 主机密钥未知或已变更时，请用户确认指纹后写入known_hosts
+  // 使用保存的连接配置时传入 profileId，由后端读取完整配置
+  const confirmHostKey = async (config: SshConfig, error: EmsError, profileId?: string) => {
+    const fingerprint: string | undefined = error.details?.fingerprint
+    if (!fingerprint) {
+      return false
+    }
+
+    // 经由跳板机连接时，出错的可能是某个跳板机
+    const host: string = error.details.host
+    const port: number = error.details.port
+    const changed = error.kind === 'HostKeyMismatch'
+    try {
+      await ElMessageBox.confirm(
+        changed
+          ? `${host}:${port} 的主机密钥已变更，可能存在中间人攻击！\n新的指纹: ${fingerprint}\n确认替换吗？`
+          : `首次连接 ${host}:${port}\n主机密钥指纹: ${fingerprint}\n确认信任该主机吗？`,
+        changed ? '主机密钥已变更' : '未知主机',
+        { type: changed ? 'error' : 'warning', confirmButtonText: '信任', cancelButtonText: '取消' }
+      )
+    } catch {
+      return false
+    }
+
+    await invoke('accept_host_key', {
+      host,
+      port,
+      fingerprint,
+      config: profileId ? null : config,
+      profileId: profileId ?? null
+    })
+    return true
+  }
+
+  // 保险库已锁定时请用户输入主密码，解锁成功返回 true
+  const unlockVault = async () => {
+    let masterPassword: string
+    try {
+      const { value } = await ElMessageBox.prompt('连接配置的密码保存在凭据保险库中，请输入主密码解锁', '解锁凭据保险库', {
+        inputType: 'password',
+        confirmButtonText: '解锁',
+        cancelButtonText: '取消'
+      })
+      masterPassword = value
+    } catch {
+      return false
+    }
+
+    try {
+      await invoke('unlock_vault', { masterPassword, idleTimeoutSecs: null })
+      return true
+    } catch (error) {
+      ElMessage.error(`解锁失败: ${formatError(error)}`)
+      return false
+    }
+  }
+
+  const connect = async (config: SshConfig, saveToHistory = true): Promise<boolean> => {
     try {
       isConnecting.value = true
       connectionStatus.value = '连接中...'
 
-      await invoke('connect', { config })
+      const result = await invoke<ConnectResult>('connect', { config })
+      console.log(`✅ [SSH-STORE] 认证方式: ${result.auth_method}`)
 
+      sessionId.value = result.session_id
       isConnected.value = true
       connectionConfig.value = config
       connectionStatus.value = '已连接'
@@ -78,8 +279,14 @@ export const useSshStore = defineStore('ssh', () => {
       ElMessage.success('SSH连接成功')
       return true
     } catch (error) {
-      connectionStatus.value = `连接失败: ${error}`
-      ElMessage.error(`连接失败: ${error}`)
+      if (isEmsError(error) && (error.kind === 'UnknownHost' || error.kind === 'HostKeyMismatch')) {
+        isConnecting.value = false
+        if (await confirmHostKey(config, error)) {
+          return await connect(config, saveToHistory)
+        }
+      }
+      connectionStatus.value = `连接失败: ${formatError(error)}`
+      ElMessage.error(`连接失败: ${formatError(error)}`)
       return false
     } finally {
       isConnecting.value = false
@@ -89,8 +296,9 @@ export const useSshStore = defineStore('ssh', () => {
   const disconnect = async () => {
     try {
       console.log('🔧 [SSH-STORE] 开始断开SSH连接')
-      await invoke('disconnect')
+      await invoke('disconnect', { sessionId: sessionId.value })
 
+      sessionId.value = null
       isConnected.value = false
       connectionConfig.value = null
       connectionStatus.value = '未连接'
@@ -102,11 +310,12 @@ export const useSshStore = defineStore('ssh', () => {
       console.error('❌ [SSH-STORE] 断开连接失败:', error)
 
       // 即使后端断开失败，也清理前端状态
+      sessionId.value = null
       isConnected.value = false
       connectionConfig.value = null
       connectionStatus.value = '断开连接失败'
 
-      ElMessage.error(`断开连接失败: ${error}`)
+      ElMessage.error(`断开连接失败: ${formatError(error)}`)
       return false
     }
   }
@@ -114,10 +323,14 @@ export const useSshStore = defineStore('ssh', () => {
   // 检查连接状态
   const checkConnectionStatus = async () => {
     try {
-      const isActive = await invoke('check_connection_status')
+      if (!sessionId.value) {
+        return false
+      }
+      const isActive = await invoke<boolean>('check_connection_status', { sessionId: sessionId.value })
       if (!isActive && isConnected.value) {
         // 连接已断开但前端状态未更新
         console.log('🔧 [SSH-STORE] 检测到连接已断开，更新前端状态')
+        sessionId.value = null
         isConnected.value = false
         connectionConfig.value = null
         connectionStatus.value = '连接已断开'
@@ -197,11 +410,96 @@ export const useSshStore = defineStore('ssh', () => {
     return await connect(config, false) // 不重复保存到历史
   }
 
+  // 加载后端保存的连接配置
+  const loadProfiles = async () => {
+    try {
+      profiles.value = await invoke<ProfileSummary[]>('list_profiles')
+    } catch (error) {
+      console.error('加载连接配置失败:', error)
+      profiles.value = []
+    }
+  }
+
+  // 新建或修改连接配置，修改时未填写的密码保持不变
+  const saveProfile = async (profile: ProfileInput, id?: string) => {
+    try {
+      if (id) {
+        await invoke<ProfileSummary>('update_profile', { id, profile })
+      } else {
+        await invoke<ProfileSummary>('add_profile', { profile })
+      }
+      await loadProfiles()
+      ElMessage.success('连接配置已保存')
+      return true
+    } catch (error) {
+      if (isEmsError(error) && error.kind === 'VaultLocked' && await unlockVault()) {
+        return await saveProfile(profile, id)
+      }
+      ElMessage.error(`保存连接配置失败: ${formatError(error)}`)
+      return false
+    }
+  }
+
+  const deleteProfile = async (id: string) => {
+    try {
+      await invoke('delete_profile', { id })
+      await loadProfiles()
+    } catch (error) {
+      ElMessage.error(`删除连接配置失败: ${formatError(error)}`)
+    }
+  }
+
+  // 使用保存的连接配置连接，密码只保存在后端
+  const connectProfile = async (profile: ProfileSummary): Promise<boolean> => {
+    const config: SshConfig = {
+      host: profile.host,
+      port: profile.port,
+      username: profile.username,
+      password: ''
+    }
+
+    try {
+      isConnecting.value = true
+      connectionStatus.value = '连接中...'
+
+      const result = await invoke<ConnectResult>('connect_profile', { id: profile.id })
+      console.log(`✅ [SSH-STORE] 认证方式: ${result.auth_method}`)
+
+      sessionId.value = result.session_id
+      isConnected.value = true
+      connectionConfig.value = config
+      connectionStatus.value = '已连接'
+      loadProfiles()
+
+      ElMessage.success('SSH连接成功')
+      return true
+    } catch (error) {
+      if (isEmsError(error) && (error.kind === 'UnknownHost' || error.kind === 'HostKeyMismatch')) {
+        isConnecting.value = false
+        if (await confirmHostKey(config, error, profile.id)) {
+          return await connectProfile(profile)
+        }
+      }
+      if (isEmsError(error) && error.kind === 'VaultLocked') {
+        isConnecting.value = false
+        if (await unlockVault()) {
+          return await connectProfile(profile)
+        }
+      }
+      connectionStatus.value = `连接失败: ${formatError(error)}`
+      ElMessage.error(`连接失败: ${formatError(error)}`)
+      return false
+    } finally {
+      isConnecting.value = false
+    }
+  }
+
   // 初始化历史记录
   loadHistory()
 
   return {
     isConnected,
+    sessionId,
     connectionConfig,
     connectionStatus,
     connectionInfo,
@@ -214,6 +512,11 @@ export const useSshStore = defineStore('ssh', () => {
     removeFromHistory,
     clearHistory,
     connectFromHistory,
-    loadHistory
+    loadHistory,
+    profiles,
+    loadProfiles,
+    saveProfile,
+    deleteProfile,
+    connectProfile
   }
 })
@@ -2,6 +2,8 @@ import { defineStore } from 'pinia'
 import { ref } from 'vue'
 import { invoke } from '@tauri-apps/api/tauri'
 import { ElMessage } from 'element-plus'
+import { useSshStore } from '@/stores/ssh'
+import { formatError, isEmsError } from '@/utils/error'
 
 // 终端输出类型枚举
 enum OutputType {
@@ -49,7 +51,9 @@ export const useTerminalStore = defineStore('terminal', () => {
   // 更新当前工作目录
   const updateCurrentDirectory = async () => {
     try {
-      const dir = await invoke<string>('get_current_directory')
+      const dir = await invoke<string>('get_current_directory', {
+        sessionId: useSshStore().sessionId
+      })
       currentDirectory.value = dir
       return dir
     } catch (error) {
@@ -75,7 +79,8 @@ export const useTerminalStore = defineStore('terminal', () => {
 
       // 执行命令
       const result = await invoke<string>('execute_terminal_command', {
-        command: command
+        command: command,
+        sessionId: useSshStore().sessionId
       })
 
       console.log('✅ [TERMINAL-STORE] 命令执行结果:', result)
@@ -94,7 +99,12 @@ export const useTerminalStore = defineStore('terminal', () => {
       return result
     } catch (error) {
       console.error('❌ [TERMINAL-STORE] 命令执行失败:', error)
-      addTypedOutput(`Error: ${error}`, OutputType.ERROR)
+      // 命令自身的错误输出直接显示
+      if (isEmsError(error) && error.kind === 'CommandFailed' && error.details?.stderr) {
+        addTypedOutput(error.details.stderr, OutputType.ERROR)
+      } else {
+        addTypedOutput(`Error: ${formatError(error)}`, OutputType.ERROR)
+      }
       addOutput('\n')
       addTypedOutput(`${currentDirectory.value}$ `, OutputType.PROMPT)
       throw error
@@ -127,7 +137,7 @@ export const useTerminalStore = defineStore('terminal', () => {
       
       ElMessage.success('批量命令执行完成')
     } catch (error) {
-      ElMessage.error(`批量命令执行失败: ${error}`)
+      ElMessage.error(`批量命令执行失败: ${formatError(error)}`)
       throw error
     } finally {
       isExecuting.value = false
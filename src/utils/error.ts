@@ -0,0 +1,36 @@
+// 后端命令返回的结构化错误
+export type EmsErrorKind =
+  | 'NotConnected'
+  | 'AuthFailed'
+  | 'UnknownHost'
+  | 'HostKeyMismatch'
+  | 'CommandFailed'
+  | 'ChecksumMismatch'
+  | 'SftpError'
+  | 'ParseError'
+  | 'LockPoisoned'
+  | 'Timeout'
+  | 'Ssh'
+  | 'Io'
+  | 'NotFound'
+  | 'InvalidInput'
+  | 'VaultLocked'
+  | 'Cancelled'
+
+export interface EmsError {
+  kind: EmsErrorKind
+  message: string
+  details: any
+}
+
+export const isEmsError = (error: unknown): error is EmsError => {
+  return typeof error === 'object' && error !== null && 'kind' in error && 'message' in error
+}
+
+// 转换为可以直接展示的错误信息
+export const formatError = (error: unknown): string => {
+  if (isEmsError(error)) {
+    return error.message
+  }
+  return String(error)
+}